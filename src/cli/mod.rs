@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::fs;
+use super::utils::ParseError;
+use super::day4;
+use super::day7;
+use super::day13;
+use super::day14;
+use super::day15;
+use super::day16;
+use super::day21;
+use super::day22;
+use super::day23;
+use super::day24;
+
+const USAGE: &str = "usage: y2020 <command> [options]
+
+commands:
+    allergens [--input FILE] [--explain]
+    passports [--input FILE] [--schema FILE] [--explain]
+    bags [--input FILE] [--bag COLOUR]
+    bags check [--input FILE] [--bag COLOUR]
+    bags export [--input FILE] [--format dot|json] [--root COLOUR] [--direction from|to|both]
+    busses [--input FILE] [--busses BUS:OFFSET,...]
+    busses next [--input FILE] [--at T]
+    busses window [--input FILE] [--busses BUS:OFFSET,...] [--from T] --to T [--limit N]
+    busses period [--input FILE] [--busses BUS:OFFSET,...]
+    combat [--input FILE] [--recursive] [--max-depth N] [--trigger all|any|up-to:N]
+           [--tie first|last|discard] [--score standard|sum|count] [--max-rounds N] [--log]
+           --max-depth N implies --recursive and allows N levels of
+           sub-games, N must be at least 1
+           --trigger picks the rounds that start a sub-game: all players,
+           any player or all players with no card above N have enough cards
+    combat simulate [--decks N] [--cards M] [--games K] [--max-rounds N] [--seed S]
+    cups [--input FILE] [--cups N] [--pick-up N] [--moves N]
+    docking [--input FILE] [--decoder 1|2] [--width BITS] [--dump]
+            --dump lists every non-zero address, addresses written through
+            floating bits are shown as one pattern like mem[0000X11X]
+    floor [--input FILE] [--days N]
+    floor shortest [--input FILE]
+    floor render [--input FILE] [--days N] [--size PX] [--animate [--seconds S] | --output DIR]
+    memory [--input FILE] [--turns N]
+    memory sequence [--input FILE] [--turns N]
+    memory stats [--input FILE] [--turns N] [--number N]
+    tickets [--input FILE] [--prefix PREFIX | --regex REGEX]
+    tickets report [--input FILE] [--format text|csv]";
+
+// options that never take a value, the word after them is a positional
+// argument or the next option
+const FLAGS: &[&str] = &["animate", "dump", "explain", "log", "recursive"];
+
+/// Command line arguments split into positional words and `--key value`
+/// options. The options in `FLAGS` and options that are not followed by a
+/// value are treated as flags.
+pub struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, Option<String>>,
+}
+
+impl Args {
+    pub fn parse(args: &[String]) -> Args {
+        let mut positional = vec![];
+        let mut options = HashMap::new();
+
+        let mut iter = args.iter().peekable();
+        while let Some(arg) = iter.next() {
+            if let Some(key) = arg.strip_prefix("--") {
+                if let Some((key, value)) = key.split_once('=') {
+                    options.insert(key.to_string(), Some(value.to_string()));
+                    continue;
+                }
+
+                let value = match iter.peek() {
+                    _ if FLAGS.contains(&key) => None,
+                    Some(next) if !next.starts_with("--") => iter.next().cloned(),
+                    _ => None,
+                };
+                options.insert(key.to_string(), value);
+            } else {
+                positional.push(arg.clone());
+            }
+        }
+
+        Args { positional, options }
+    }
+
+    pub fn positional(&self, index: usize) -> Option<&str> {
+        self.positional.get(index).map(|v| v.as_str())
+    }
+
+    pub fn flag(&self, key: &str) -> bool {
+        self.options.contains_key(key)
+    }
+
+    pub fn option(&self, key: &str) -> Option<&str> {
+        self.options.get(key).and_then(|v| v.as_deref())
+    }
+
+    pub fn parsed_option<T: std::str::FromStr>(&self, key: &str) -> Result<Option<T>, ParseError> {
+        self.option(key)
+            .map(|v| v.parse::<T>().map_err(|_| ParseError::new(&format!("Invalid value for --{}: {}", key, v))))
+            .transpose()
+    }
+
+    /// Contents of the file given with `--input`, or the built-in puzzle
+    /// input if none was given.
+    pub fn input(&self, default: &str) -> Result<String, ParseError> {
+        match self.option("input") {
+            Some(path) => Ok(fs::read_to_string(path)?),
+            None => Ok(default.to_string()),
+        }
+    }
+}
+
+pub fn run(args: &[String]) -> Result<(), ParseError> {
+    let args = Args::parse(args);
+
+    match args.positional(0) {
+        Some("allergens") => day21::command(&args),
+        Some("passports") => day4::command(&args),
+        Some("bags") => day7::command(&args),
+        Some("busses") => day13::command(&args),
+        Some("combat") => day22::command(&args),
+        Some("cups") => day23::command(&args),
+        Some("docking") => day14::command(&args),
+        Some("floor") => day24::command(&args),
+        Some("memory") => day15::command(&args),
+        Some("tickets") => day16::command(&args),
+        _ => Err(ParseError::new(USAGE)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(s: &str) -> Args {
+        let v = s.split(' ').map(|v| v.to_string()).collect::<Vec<_>>();
+        Args::parse(&v)
+    }
+
+    #[test]
+    pub fn options_and_flags_are_separated() {
+        let a = args("bags export --format dot --explain --root=red");
+
+        assert_eq!(Some("bags"), a.positional(0));
+        assert_eq!(Some("export"), a.positional(1));
+        assert_eq!(Some("dot"), a.option("format"));
+        assert!(a.flag("explain"));
+        assert_eq!(None, a.option("explain"));
+        assert_eq!(Some("red"), a.option("root"));
+    }
+
+    #[test]
+    pub fn flags_do_not_take_positional_words() {
+        let a = args("floor --animate render --seconds 5");
+
+        assert_eq!(Some("floor"), a.positional(0));
+        assert_eq!(Some("render"), a.positional(1));
+        assert!(a.flag("animate"));
+        assert_eq!(None, a.option("animate"));
+        assert_eq!(Some("5"), a.option("seconds"));
+    }
+}
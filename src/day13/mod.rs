@@ -1,4 +1,10 @@
+use num::BigInt;
 use super::utils::ParseError;
+use super::cli::Args;
+
+mod schedule;
+
+use schedule::{earliest_timestamp, timestamps_in_window, parse_offsets, period};
 
 #[derive(Debug)]
 struct Terminal {
@@ -6,33 +12,44 @@ struct Terminal {
     busses: Vec<Option<usize>>,
 }
 
-fn parse_input() -> Result<Terminal, ParseError> {
-    let input = include_str!("./data/input.txt");
+fn parse_input(input: &str) -> Result<Terminal, ParseError> {
     let lines = input
         .lines()
         .filter(|v| *v != "")
         .collect::<Vec<_>>();
 
-    let timestamp = lines[0].parse::<usize>()?;
+    if lines.len() < 2 {
+        return Err(ParseError::new("Expected a timestamp and a list of busses"));
+    }
+
+    let timestamp = lines[0].trim().parse::<usize>()?;
     let busses = lines[1]
         .split(',')
-        .map(|v| v.parse::<usize>().ok())
-        .collect::<Vec<_>>();
+        .map(parse_bus)
+        .collect::<Result<Vec<_>, ParseError>>()?;
 
     Ok(Terminal { timestamp, busses })
 }
 
-pub fn problem1() -> Result<(), ParseError> {
-    let terminal = parse_input()?;
+// a bus id or x for a bus that is out of service
+fn parse_bus(s: &str) -> Result<Option<usize>, ParseError> {
+    let s = s.trim();
+    if s == "x" {
+        return Ok(None);
+    }
 
-    let mut next_arrivals = terminal.busses.iter()
-        .filter(|b| b.is_some())
-        .map(|b| b.unwrap())
-        .map(|b| (b, (terminal.timestamp - (terminal.timestamp % b)) + b))
-        .collect::<Vec<_>>();
+    match s.parse::<usize>() {
+        Ok(0) => Err(ParseError::new("Bus ids must be positive")),
+        Ok(bus) => Ok(Some(bus)),
+        Err(_) => Err(ParseError::new(&format!("Not a valid bus id: '{}'", s))),
+    }
+}
 
-    next_arrivals.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
-    let next = next_arrivals.iter().next().ok_or(ParseError::new("No bus found"))?;
+pub fn problem1() -> Result<(), ParseError> {
+    let terminal = parse_input(include_str!("./data/input.txt"))?;
+
+    let departures = terminal.next_departures(terminal.timestamp)?;
+    let next = departures.first().ok_or(ParseError::new("No bus found"))?;
 
     let arrives_in = next.1 - terminal.timestamp;
     println!("Next arriving bus is {} in {} minutes.", next.0, arrives_in);
@@ -41,95 +58,130 @@ pub fn problem1() -> Result<(), ParseError> {
     Ok(())
 }
 
-struct Crt {
-    remainder: usize,
-    modulus: usize,
-}
+pub fn problem2() -> Result<(), ParseError> {
+    let terminal = parse_input(include_str!("./data/input.txt"))?;
 
-fn chinese_remainder(crts: &Vec<Crt>) -> usize {
-    // search the solution with the chinese remainder theorem
-    // https://en.wikipedia.org/wiki/Chinese_remainder_theorem#Search_by_sieving
+    let result = earliest_timestamp(&terminal.offsets())?;
+    println!("13/2: result {}", result);
 
-    let mut time = crts[0].remainder;
-    let mut increment = crts[0].modulus;
+    Ok(())
+}
 
-    for crt in crts.iter().skip(1) {
-        loop {
-            if time % crt.modulus == crt.remainder {
-                break;
+// the number of timestamps a window query prints by default
+const WINDOW_LIMIT: usize = 100;
+
+pub fn command(args: &Args) -> Result<(), ParseError> {
+    let terminal = parse_input(&args.input(include_str!("./data/input.txt"))?)?;
+    let offsets = match args.option("busses") {
+        Some(busses) => parse_offsets(busses)?,
+        None => terminal.offsets(),
+    };
+
+    match args.positional(1) {
+        Some("next") => {
+            let timestamp = args.parsed_option::<usize>("at")?.unwrap_or(terminal.timestamp);
+            for (bus, departure) in terminal.next_departures(timestamp)? {
+                println!("bus {} departs at {} (in {} minutes)", bus, departure, departure - timestamp);
+            }
+        },
+        Some("window") => {
+            let from = args.parsed_option::<BigInt>("from")?.unwrap_or_default();
+            let to = args.parsed_option::<BigInt>("to")?
+                .ok_or_else(|| ParseError::new("The end of the window is missing (--to)"))?;
+            let limit = args.parsed_option::<usize>("limit")?.unwrap_or(WINDOW_LIMIT);
+            for t in timestamps_in_window(&offsets, &from, &to, limit)? {
+                println!("{}", t);
             }
+        },
+        Some("period") => println!("{}", period(&offsets)),
+        _ => println!("earliest timestamp: {}", earliest_timestamp(&offsets)?),
+    }
 
-            time += increment;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn example_1() {
+        let terminal = parse_input(include_str!("./data/example1.txt")).unwrap();
+        let departures = terminal.next_departures(terminal.timestamp).unwrap();
+
+        assert_eq!((59, 944), departures[0]);
+        assert_eq!((7, 945), departures[1]);
+        assert_eq!((19, 19), terminal.next_departures(14).unwrap()[0]);
+    }
+
+    #[test]
+    pub fn busses_leaving_at_the_timestamp_are_missed() {
+        let terminal = parse_input("14\n7,13").unwrap();
+        assert_eq!(vec![(7, 21), (13, 26)], terminal.next_departures(terminal.timestamp).unwrap());
+    }
+
+    #[test]
+    pub fn departures_out_of_range_are_reported() {
+        let terminal = parse_input("1\n1,19").unwrap();
+        assert!(terminal.next_departures(18446744073709551600).is_err());
+        assert!(terminal.next_departures(usize::MAX).is_err());
+        assert_eq!(vec![(1, 2), (19, 19)], terminal.next_departures(1).unwrap());
+    }
+
+    #[test]
+    pub fn example_2() {
+        let examples = vec![
+            (include_str!("./data/example1.txt"), 1068781),
+            (include_str!("./data/example2.txt"), 3417),
+            (include_str!("./data/example3.txt"), 754018),
+            (include_str!("./data/example4.txt"), 779210),
+            (include_str!("./data/example5.txt"), 1261476),
+            (include_str!("./data/example6.txt"), 1202161486),
+        ];
+
+        for (input, expected) in examples {
+            let terminal = parse_input(input).unwrap();
+            assert_eq!(BigInt::from(expected), earliest_timestamp(&terminal.offsets()).unwrap());
         }
+    }
 
-        increment *= crt.modulus;
+    #[test]
+    pub fn bus_ids_need_not_be_coprime() {
+        let terminal = parse_input("1\n4,x,6").unwrap();
+        assert_eq!(BigInt::from(4), earliest_timestamp(&terminal.offsets()).unwrap());
+        assert_eq!(BigInt::from(12), period(&terminal.offsets()));
+
+        let terminal = parse_input("1\n4,6").unwrap();
+        assert!(earliest_timestamp(&terminal.offsets()).is_err());
     }
 
-    time
-}
+    #[test]
+    pub fn bus_ids_may_exceed_i64() {
+        let offsets = parse_offsets("9223372036854775809:0,2:1").unwrap();
+        assert_eq!(BigInt::from(9223372036854775809u64), earliest_timestamp(&offsets).unwrap());
+    }
 
-pub fn problem2() -> Result<(), ParseError> {
-    let terminal = parse_input()?;
-
-    let mut busses = terminal.busses.into_iter()
-        .enumerate()
-        // Filter each irrelevant bus
-        .filter(|(_, b)| b.is_some())
-
-        // The timestamp t we are looking for has to fulfill the equations
-        //
-        //   t + offset_i mod bus_i = 0
-        //
-        // but the CRT is giving us instructions on how to solve
-        //
-        //   t mod bus_i = a_i
-        //
-        // for a_i with
-        //
-        //   0 <= a_i < bus_i
-        //
-        // So we have to get the a_i from our input. This is easy but we have
-        // to consider a few corner cases. In case
-        //
-        //   0 < offset_i < bus_i
-        //
-        // we get
-        //
-        //   a_i = bus_i - offset_i
-        //
-        // We now have to consider two special cases:
-        //
-        //   offset_i = 0
-        //
-        // and
-        //
-        //   offset_i > bus_id
-        //
-        // To eliminate the first one we can apply the modulus operation on the
-        // difference:
-        //
-        //   (bus_i - offset_i) % bus_i
-        //
-        // To eliminate the second issue, we can apply the modulus operation on
-        // the offset directly:
-        //
-        //   (bus_i - offset_i % bus_i)
-        //
-        // Putting everything together we now have:
-        //
-        //   a_i = (bus_i - offset_i % bus_i) % bus_i
-        //
-        // which is exactly how we are calculating the first component of
-        // belows result tuple:
-        .map(|(i, b)| ((b.unwrap() - i % b.unwrap()) % b.unwrap(), b.unwrap()))
-        .map(|(remainder, modulus)| Crt { remainder, modulus })
-        .collect::<Vec<_>>();
+    #[test]
+    pub fn window_queries() {
+        let terminal = parse_input(include_str!("./data/example1.txt")).unwrap();
+        assert_eq!(BigInt::from(7 * 13 * 59 * 31 * 19), period(&terminal.offsets()));
+        assert_eq!(BigInt::from(7 * 13), period(&parse_offsets("7:0,13:1").unwrap()));
 
-    // sort descending by bus id
-    busses.sort_by(|a, b| b.modulus.partial_cmp(&a.modulus).unwrap());
+        let offsets = parse_offsets("7:0,13:1").unwrap();
+        let timestamps = timestamps_in_window(&offsets, &BigInt::from(0), &BigInt::from(200), 10).unwrap();
+        assert_eq!(vec![BigInt::from(77), BigInt::from(168)], timestamps);
 
-    let result = chinese_remainder(&busses);
-    println!("13/2: result {}", result);
+        let timestamps = timestamps_in_window(&offsets, &BigInt::from(78), &BigInt::from(168), 10).unwrap();
+        assert_eq!(vec![BigInt::from(168)], timestamps);
 
-    Ok(())
-}
\ No newline at end of file
+        let huge = BigInt::from(10).pow(30);
+        assert_eq!(3, timestamps_in_window(&offsets, &BigInt::from(0), &huge, 3).unwrap().len());
+
+        assert!(parse_offsets("7:0,13").is_err());
+        assert!(parse_offsets("7:0,0:1").is_err());
+        assert!(parse_input("1\n7,0,13").is_err());
+        assert!(parse_input("1\n7,7a,13").is_err());
+        assert!(parse_input("939").is_err());
+        assert!(parse_input("").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use std::str::FromStr;
+use num::{BigInt, Integer, One};
+use super::super::utils::ParseError;
+use super::super::utils::math::{Congruence, chinese_remainder};
+use super::Terminal;
+
+/// A bus that has to depart `offset` minutes after a timestamp.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Offset {
+    pub bus: usize,
+    pub offset: usize,
+}
+
+impl FromStr for Offset {
+    type Err = ParseError;
+
+    // <bus>:<offset>
+    fn from_str(s: &str) -> Result<Offset, ParseError> {
+        let (bus, offset) = s.split_once(':')
+            .ok_or_else(|| ParseError::new(&format!("Not a valid bus offset: '{}'", s)))?;
+
+        let bus = bus.trim().parse()?;
+        if bus == 0 {
+            return Err(ParseError::new("Bus ids must be positive"));
+        }
+
+        Ok(Offset { bus, offset: offset.trim().parse()? })
+    }
+}
+
+pub fn parse_offsets(s: &str) -> Result<Vec<Offset>, ParseError> {
+    s.split(',')
+        .map(Offset::from_str)
+        .collect()
+}
+
+impl Terminal {
+    pub fn bus_ids(&self) -> impl Iterator<Item = usize> + '_ {
+        self.busses.iter().filter_map(|b| *b)
+    }
+
+    /// The busses with their position in the schedule as offset.
+    pub fn offsets(&self) -> Vec<Offset> {
+        self.busses.iter()
+            .enumerate()
+            .filter_map(|(offset, b)| b.map(|bus| Offset { bus, offset }))
+            .collect()
+    }
+
+    /// The first departure of every bus strictly after the given timestamp,
+    /// the earliest departure first.
+    pub fn next_departures(&self, timestamp: usize) -> Result<Vec<(usize, usize)>, ParseError> {
+        let mut departures = self.bus_ids()
+            .map(|b| (timestamp / b).checked_add(1).and_then(|n| n.checked_mul(b))
+                .map(|t| (b, t))
+                .ok_or_else(|| ParseError::new(&format!("The next departure of bus {} after {} is out of range", b, timestamp))))
+            .collect::<Result<Vec<_>, ParseError>>()?;
+
+        departures.sort_by_key(|&(b, t)| (t, b));
+        Ok(departures)
+    }
+}
+
+/// After this many minutes all the given busses depart at the same times
+/// again.
+pub fn period(offsets: &[Offset]) -> BigInt {
+    offsets.iter()
+        .fold(BigInt::one(), |p, o| p.lcm(&BigInt::from(o.bus)))
+}
+
+// The timestamp t has to fulfill t + offset_i = 0 (mod bus_i) for every bus,
+// i.e. t = -offset_i (mod bus_i). The bus ids don't have to be coprime, if
+// the system has no solution at all an error is returned.
+fn solve(offsets: &[Offset]) -> Result<Congruence, ParseError> {
+    if offsets.iter().any(|o| o.bus == 0) {
+        return Err(ParseError::new("Bus ids must be positive"));
+    }
+
+    let congruences = offsets.iter()
+        .map(|o| Congruence::new(-BigInt::from(o.offset), BigInt::from(o.bus)))
+        .collect::<Vec<_>>();
+
+    chinese_remainder(&congruences)
+}
+
+/// The earliest timestamp at which every bus departs at its offset.
+pub fn earliest_timestamp(offsets: &[Offset]) -> Result<BigInt, ParseError> {
+    Ok(solve(offsets)?.remainder)
+}
+
+/// The first `limit` timestamps from `from` to `to`, both inclusive, at which
+/// every bus departs at its offset.
+pub fn timestamps_in_window(offsets: &[Offset], from: &BigInt, to: &BigInt, limit: usize) -> Result<Vec<BigInt>, ParseError> {
+    let solution = solve(offsets)?;
+
+    // the first solution at or after from
+    let mut t = from + (&solution.remainder - from).mod_floor(&solution.modulus);
+    let mut timestamps = vec![];
+    while &t <= to && timestamps.len() < limit {
+        timestamps.push(t.clone());
+        t += &solution.modulus;
+    }
+
+    Ok(timestamps)
+}
@@ -0,0 +1,106 @@
+use num::{BigUint, One};
+
+/// A set of addresses: every bit set in `floating` can be 0 or 1, all
+/// other bits are the ones of `fixed`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Pattern {
+    fixed: u128,
+    floating: u128,
+}
+
+impl Pattern {
+    pub fn new(fixed: u128, floating: u128) -> Pattern {
+        Pattern { fixed: fixed & !floating, floating }
+    }
+
+    /// The number of addresses matching the pattern.
+    pub fn size(&self) -> BigUint {
+        BigUint::one() << self.floating.count_ones()
+    }
+
+    /// The address if the pattern matches only one.
+    pub fn address(&self) -> Option<u128> {
+        if self.floating == 0 { Some(self.fixed) } else { None }
+    }
+
+    /// The lowest `width` bits of the pattern with X for floating bits.
+    pub fn format(&self, width: u32) -> String {
+        (0..width).rev()
+            .map(|i| {
+                let bit = 1_u128 << i;
+                if self.floating & bit != 0 {
+                    'X'
+                } else if self.fixed & bit != 0 {
+                    '1'
+                } else {
+                    '0'
+                }
+            })
+            .collect()
+    }
+
+    fn intersects(&self, other: &Pattern) -> bool {
+        (self.fixed ^ other.fixed) & !self.floating & !other.floating == 0
+    }
+
+    // splits the addresses of self that don't match other into disjoint
+    // patterns: for every bit that is floating in self but fixed in other
+    // one pattern takes the opposite value of other, all bits handled before
+    // take the value of other.
+    fn subtract(&self, other: &Pattern) -> Vec<Pattern> {
+        if !self.intersects(other) {
+            return vec![*self];
+        }
+
+        let mut pieces = vec![];
+        let mut rest = *self;
+        let split = self.floating & !other.floating;
+
+        for i in 0..128 {
+            let bit = 1_u128 << i;
+            if split & bit == 0 {
+                continue;
+            }
+
+            rest.floating &= !bit;
+            pieces.push(Pattern { fixed: rest.fixed | (!other.fixed & bit), floating: rest.floating });
+            rest.fixed |= other.fixed & bit;
+        }
+
+        pieces
+    }
+}
+
+/// Memory written through address patterns. Instead of storing every
+/// address, the memory is kept as disjoint patterns that share a value: a
+/// new write removes its addresses from all existing patterns first.
+#[derive(Default)]
+pub struct FloatingMemory {
+    regions: Vec<(Pattern, u128)>,
+}
+
+impl FloatingMemory {
+    pub fn write(&mut self, pattern: Pattern, value: u128) {
+        self.regions = self.regions.iter()
+            .flat_map(|(p, v)| p.subtract(&pattern).into_iter().map(move |p| (p, *v)))
+            .collect();
+
+        if value != 0 {
+            self.regions.push((pattern, value));
+        }
+    }
+
+    /// The sum of the values of all addresses.
+    pub fn sum(&self) -> BigUint {
+        self.regions.iter()
+            .map(|(p, v)| p.size() * BigUint::from(*v))
+            .sum()
+    }
+
+    /// All patterns with a non-zero value, ordered by their lowest address.
+    pub fn regions(&self) -> Vec<(Pattern, u128)> {
+        let mut regions = self.regions.clone();
+        regions.sort();
+        regions
+    }
+}
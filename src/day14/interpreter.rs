@@ -0,0 +1,92 @@
+use std::fmt::Write;
+use num::BigUint;
+use super::super::utils::ParseError;
+use super::{Command, Mask};
+use super::floating::{Pattern, FloatingMemory};
+
+pub const MAX_WIDTH: u32 = 128;
+
+/// Decides what a write does under the current mask: which addresses it
+/// writes to and which value.
+pub trait Decoder {
+    fn decode(&self, mask: &Mask, address: u128, value: u128) -> (Pattern, u128);
+}
+
+/// Version 1: the mask overwrites bits of the value, X leaves them as they are.
+pub struct ValueDecoder;
+
+impl Decoder for ValueDecoder {
+    fn decode(&self, mask: &Mask, address: u128, value: u128) -> (Pattern, u128) {
+        (Pattern::new(address, 0), (value & mask.floating) | mask.ones)
+    }
+}
+
+/// Version 2: the mask sets bits of the address, X bits take every value.
+pub struct AddressDecoder;
+
+impl Decoder for AddressDecoder {
+    fn decode(&self, mask: &Mask, address: u128, value: u128) -> (Pattern, u128) {
+        (Pattern::new(address | mask.ones, mask.floating), value)
+    }
+}
+
+pub fn decoder(version: &str) -> Result<Box<dyn Decoder>, ParseError> {
+    match version {
+        "1" => Ok(Box::new(ValueDecoder)),
+        "2" => Ok(Box::new(AddressDecoder)),
+        _ => Err(ParseError::new(&format!("Unknown decoder version: {}", version))),
+    }
+}
+
+/// Runs a docking program on memory with words of `width` bits.
+pub struct Interpreter {
+    width: u32,
+    decoder: Box<dyn Decoder>,
+    mask: Mask,
+    memory: FloatingMemory,
+}
+
+impl Interpreter {
+    pub fn new(width: u32, decoder: Box<dyn Decoder>) -> Result<Interpreter, ParseError> {
+        if width == 0 || width > MAX_WIDTH {
+            return Err(ParseError::new(&format!("Word width must be between 1 and {} bits", MAX_WIDTH)));
+        }
+
+        Ok(Interpreter { width, decoder, mask: Mask::default(), memory: FloatingMemory::default() })
+    }
+
+    pub fn run(&mut self, commands: &[Command]) {
+        for c in commands {
+            match c {
+                Command::Mask(m) => {
+                    self.mask = *m;
+                },
+                Command::Write((a, v)) => {
+                    let (pattern, value) = self.decoder.decode(&self.mask, *a, *v);
+                    self.memory.write(pattern, value);
+                }
+            }
+        }
+    }
+
+    pub fn sum(&self) -> BigUint {
+        self.memory.sum()
+    }
+
+    /// One line per address with a non-zero value in ascending order.
+    /// Addresses written through floating bits are not expanded, there may
+    /// be up to 2^128 of them, but shown as one pattern with X for every
+    /// floating bit.
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+
+        for (pattern, value) in self.memory.regions() {
+            match pattern.address() {
+                Some(a) => writeln!(out, "mem[{}] = {}", a, value).unwrap(),
+                None => writeln!(out, "mem[{}] = {}", pattern.format(self.width), value).unwrap(),
+            }
+        }
+
+        out
+    }
+}
@@ -1,14 +1,29 @@
-use std::collections::{HashMap, HashSet};
 use regex::Regex;
 use super::utils::ParseError;
+use super::cli::Args;
+
+mod floating;
+mod interpreter;
+
+use interpreter::{Interpreter, decoder, MAX_WIDTH};
+
+// the word width of the docking program in the puzzle
+const WIDTH: u32 = 36;
+
+/// Bits set in `floating` are X in the mask, bits set in `ones` are 1.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Mask {
+    pub floating: u128,
+    pub ones: u128,
+}
 
 #[derive(Debug)]
-enum Command {
-    Mask((u64, u64)),
-    Write((usize, u64)),
+pub enum Command {
+    Mask(Mask),
+    Write((u128, u128)),
 }
 
-fn parse_command(s: &str) -> Result<Command, ParseError> {
+fn parse_command(s: &str, width: u32) -> Result<Command, ParseError> {
     lazy_static!{
         static ref RE_MASK: Regex = Regex::new(r"^mask = ([01X]+)$").unwrap();
         static ref RE_WRITE: Regex = Regex::new(r"^mem\[(\d+)\] = (\d+)$").unwrap();
@@ -19,6 +34,10 @@ fn parse_command(s: &str) -> Result<Command, ParseError> {
     if is_mask {
         let cap = RE_MASK.captures(s).ok_or(ParseError::new(&format!("Could not parse '{}' as mask", s)))?;
 
+        if cap[1].len() != width as usize {
+            return Err(ParseError::new(&format!("Mask '{}' does not have {} bits", &cap[1], width)));
+        }
+
         let mask: String = cap[1].chars()
             .map(|v| if v == 'X' {
                 '1'
@@ -35,120 +54,126 @@ fn parse_command(s: &str) -> Result<Command, ParseError> {
             })
             .collect();
 
-        let mask = u64::from_str_radix(&mask, 2)?;
-        let overwrite = u64::from_str_radix(&overwrite, 2)?;
-        Ok(Command::Mask((mask, overwrite)))
+        let floating = u128::from_str_radix(&mask, 2)?;
+        let ones = u128::from_str_radix(&overwrite, 2)?;
+        Ok(Command::Mask(Mask { floating, ones }))
     } else {
         let cap = RE_WRITE.captures(s).ok_or(ParseError::new(&format!("Could not parse '{}' as write", s)))?;
-        let address = cap[1].parse::<usize>()?;
-        let value = cap[2].parse::<u64>()?;
+        let address = cap[1].parse::<u128>()?;
+        let value = cap[2].parse::<u128>()?;
+
+        if width < MAX_WIDTH && (address >> width != 0 || value >> width != 0) {
+            return Err(ParseError::new(&format!("'{}' does not fit into {} bits", s, width)));
+        }
+
         Ok(Command::Write((address, value)))
     }
 }
 
-fn parse_input() -> Result<Vec<Command>, ParseError> {
-    let input = include_str!("./data/input.txt");
+fn parse_input(input: &str, width: u32) -> Result<Vec<Command>, ParseError> {
     input
         .lines()
         .filter(|v| *v != "")
-        .map(|v| parse_command(v))
+        .map(|v| parse_command(v, width))
         .collect::<Result<Vec<_>, ParseError>>()
 }
 
-pub fn problem1() -> Result<(), ParseError> {
-    let commands = parse_input()?;
-
-    let buffer_max = commands.iter()
-        .map(|c| match c {
-            Command::Write((a, _)) => *a,
-            Command::Mask(_) => 0,
-        })
-        .max()
-        .ok_or(ParseError::new("Could not determine memory size."))?;
-
-    let mut memory = vec![0; buffer_max + 1];
-    let mut mask = (0, 0);
-
-    for c in &commands {
-        match c {
-            Command::Mask(m) => {
-                mask = *m;
-            },
-            Command::Write((a, v)) => {
-                memory[*a] = (v & mask.0) | mask.1;
-            }
-        }
-    }
+fn run(input: &str, width: u32, version: &str) -> Result<Interpreter, ParseError> {
+    let mut interpreter = Interpreter::new(width, decoder(version)?)?;
+    let commands = parse_input(input, width)?;
+    interpreter.run(&commands);
 
-    let result: u64 = memory.iter()
-        .filter(|&v| *v != 0)
-        .sum();
+    Ok(interpreter)
+}
+
+pub fn problem1() -> Result<(), ParseError> {
+    let result = run(include_str!("./data/input.txt"), WIDTH, "1")?.sum();
 
     println!("14/1: memory init result is: {}", result);
 
     Ok(())
 }
 
-fn variants(mask: usize) -> Vec<usize> {
-    let mut variants = HashSet::new();
-    variants.insert(0);
+pub fn problem2() -> Result<(), ParseError> {
+    let result = run(include_str!("./data/input.txt"), WIDTH, "2")?.sum();
 
-    for i in 0..36 {
-        let bit = (1_usize << i) as usize;
+    println!("14/2: memory init result is: {}", result);
 
-        let candidate = mask & bit;
-        if candidate != 0 {
-            variants.insert(candidate);
+    Ok(())
+}
 
-            let mut new_variants = vec![];
-            for v in &variants {
-                new_variants.push(v | candidate);
-            }
+pub fn command(args: &Args) -> Result<(), ParseError> {
+    let input = args.input(include_str!("./data/input.txt"))?;
+    let width = args.parsed_option::<u32>("width")?.unwrap_or(WIDTH);
+    let interpreter = run(&input, width, args.option("decoder").unwrap_or("2"))?;
 
-            for v in new_variants {
-                variants.insert(v);
-            }
-        }
+    if args.flag("dump") {
+        print!("{}", interpreter.dump());
+    } else {
+        println!("sum of all values in memory: {}", interpreter.sum());
     }
 
-    variants.into_iter().collect()
+    Ok(())
 }
 
-fn write(memory: &mut HashMap<usize, u64>, mask: u64, overwrite: u64, address: usize, value: u64) {
-    let overwrite = overwrite as usize;
-    let mask = mask as usize;
+#[cfg(test)]
+mod test {
+    use super::*;
+    use num::BigUint;
 
-    let variants = variants(mask);
+    fn sum(input: &str, width: u32, version: &str) -> BigUint {
+        run(input, width, version).unwrap().sum()
+    }
 
-    for variant in variants {
-        let a = ((address | overwrite) & !mask) | variant;
+    #[test]
+    pub fn example_1_1() {
+        assert_eq!(BigUint::from(165_u32), sum(include_str!("./data/example.txt"), WIDTH, "1"));
+    }
 
-        memory.entry(a).and_modify(|v| *v = value).or_insert(value);
+    #[test]
+    pub fn example_2_1() {
+        assert_eq!(BigUint::from(208_u32), sum(include_str!("./data/example2.txt"), WIDTH, "2"));
     }
-}
 
-pub fn problem2() -> Result<(), ParseError> {
-    let commands = parse_input()?;
-
-    let mut memory = HashMap::new();
-    let mut mask = (0, 0);
-
-    for c in &commands {
-        match c {
-            Command::Mask(m) => {
-                mask = *m;
-            },
-            Command::Write((a, v)) => {
-                write(&mut memory, mask.0, mask.1, *a, *v);
-            }
-        }
+    #[test]
+    pub fn overlapping_writes() {
+        let input = "mask = 000000000000000000000000000000XX00XX\n\
+                     mem[0] = 3\n\
+                     mask = 000000000000000000000000000000X0000X\n\
+                     mem[0] = 5\n\
+                     mem[2] = 0";
+        assert_eq!(BigUint::from(8_u32 * 3 + 4 * 5), sum(input, WIDTH, "2"));
     }
 
-    let result: u64 = memory.iter()
-        .map(|(_, v)| *v)
-        .sum();
+    #[test]
+    pub fn all_bits_floating() {
+        let input = "mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX\n\
+                     mem[8] = 7\n\
+                     mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX0\n\
+                     mem[1] = 1";
+        assert_eq!(BigUint::from((1_u64 << 35) * 7 + (1 << 35)), sum(input, WIDTH, "2"));
 
-    println!("14/2: memory init result is: {}", result);
+        let input = format!("mask = {}\nmem[0] = {}", "X".repeat(128), u128::MAX);
+        assert_eq!(BigUint::from(u128::MAX) << 128, sum(&input, 128, "2"));
+    }
 
-    Ok(())
+    #[test]
+    pub fn word_width() {
+        let input = "mask = 1XXX0X0X\nmem[3] = 255\nmem[1] = 7";
+        let interpreter = run(input, 8, "1").unwrap();
+        assert_eq!(BigUint::from(0b1111_0101_u32 + 0b1000_0101), interpreter.sum());
+        assert_eq!("mem[1] = 133\nmem[3] = 245\n", interpreter.dump());
+
+        let interpreter = run("mask = 0000X01X\nmem[4] = 9", 8, "2").unwrap();
+        assert_eq!("mem[0000X11X] = 9\n", interpreter.dump());
+
+        assert!(run(input, 36, "1").is_err());
+        assert!(run("mask = 0000X01X\nmem[256] = 1", 8, "2").is_err());
+        assert!(run(input, 8, "3").is_err());
+        assert!(run("", 129, "1").is_err());
+
+        let input = format!("mask = {}\nmem[0] = 1", "X".repeat(200));
+        let error = run(&input, 200, "2").err().unwrap();
+        assert_eq!("Word width must be between 1 and 128 bits", error.to_string());
+    }
 }
@@ -1,56 +1,151 @@
-use std::collections::HashMap;
 use super::utils::ParseError;
+use super::cli::Args;
 
-fn run(start: Vec<i32>, max: usize) -> Result<i32, ParseError> {
+mod stats;
+
+use stats::Statistics;
+
+fn parse_input(input: &str) -> Result<Vec<u32>, ParseError> {
+    input
+        .split([',', '\n'])
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .map(|v| v.parse::<u32>().map_err(ParseError::from))
+        .collect::<Result<Vec<_>, ParseError>>()
+}
+
+fn run(start: &[u32], max: usize) -> Result<u32, ParseError> {
     let s = start.len();
-    let mut m = HashMap::new();
 
-    // don't store the last known index of the last number, this is done in the
+    if s == 0 {
+        return Err(ParseError::new("No starting numbers given."));
+    }
+
+    if max == 0 {
+        return Err(ParseError::new("The game starts with turn 1."));
+    }
+
+    if max <= s {
+        return Ok(start[max - 1]);
+    }
+
+    if max > u32::MAX as usize {
+        return Err(ParseError::new("Too many turns."));
+    }
+
+    // every number spoken after the starting numbers is smaller than the
+    // number of turns so we can index the turn a number was last spoken by
+    // the number itself. a 0 means the number has not been spoken yet, turns
+    // are counted starting at 1.
+    let largest = *start.iter().max().unwrap() as usize;
+    let mut last_seen: Vec<u32> = vec![0; max.max(largest + 1)];
+
+    // don't store the turn of the last starting number, this is done in the
     // main loop below. otherwise, we can't determine the second-to-last time
     // number was spoken which is what we actually need for the main loop.
-    for (i, r) in start.iter().cloned().enumerate().take(start.len() - 1) {
-        m.entry(r).or_insert(i);
+    for (i, &n) in start.iter().enumerate().take(s - 1) {
+        last_seen[n as usize] = (i + 1) as u32;
     }
 
-    let mut record: Vec<i32> = Vec::with_capacity(max + 2);
-    let mut start = start;
-    record.append(&mut start);
+    let mut last = start[s - 1];
+    for turn in s as u32..max as u32 {
+        let seen = last_seen[last as usize];
+        last_seen[last as usize] = turn;
+        last = if seen == 0 { 0 } else { turn - seen };
+    }
 
-    for i in s..max {
-        let last = *record.get(record.len() - 1).ok_or(ParseError::new("Empty record found."))?;
-        let last_index = m.get(&last);
+    Ok(last)
+}
 
-        if let Some(n) = last_index {
-            let new = (i - *n - 1) as i32;
-            record.push(new);
-            m.entry(last).and_modify(|v| *v = i - 1);
-        } else {
-            record.push(0);
-            m.entry(last).or_insert(i - 1);
-        }
+/// Streams the numbers spoken in the game, starting with turn 1. Unlike `run`
+/// the memory grows with the largest number spoken so far.
+pub struct Game<'a> {
+    start: &'a [u32],
+    last_seen: Vec<usize>,
+    turn: usize,
+    next: u32,
+}
+
+impl<'a> Game<'a> {
+    pub fn new(start: &'a [u32]) -> Game<'a> {
+        Game { start, last_seen: vec![], turn: 0, next: 0 }
     }
+}
+
+impl<'a> Iterator for Game<'a> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        if self.start.is_empty() {
+            return None;
+        }
+
+        self.turn += 1;
+        let spoken = self.start.get(self.turn - 1).cloned().unwrap_or(self.next);
+        let index = spoken as usize;
+
+        if index >= self.last_seen.len() {
+            self.last_seen.resize(index + 1, 0);
+        }
 
-    Ok(*record.last().unwrap())
+        let seen = self.last_seen[index];
+        self.last_seen[index] = self.turn;
+        self.next = if seen == 0 { 0 } else { (self.turn - seen) as u32 };
+
+        Some(spoken)
+    }
 }
 
 pub fn problem1() -> Result<(), ParseError> {
-    let input = vec![1,0,15,2,10,13];
+    let input = parse_input(include_str!("./data/input.txt"))?;
 
-    let result = run(input, 2020)?;
+    let result = run(&input, 2020)?;
     println!("15/1: last number is {}", result);
 
     Ok(())
 }
 
 pub fn problem2() -> Result<(), ParseError> {
-    let input = vec![1,0,15,2,10,13];
+    let input = parse_input(include_str!("./data/input.txt"))?;
 
-    let result = run(input, 30_000_000)?;
+    let result = run(&input, 30_000_000)?;
     println!("15/2: last number is {}", result);
 
     Ok(())
 }
 
+pub fn command(args: &Args) -> Result<(), ParseError> {
+    let input = parse_input(&args.input(include_str!("./data/input.txt"))?)?;
+    let turns = args.parsed_option::<usize>("turns")?.unwrap_or(2020);
+
+    match args.positional(1) {
+        Some("sequence") => {
+            for n in Game::new(&input).take(turns) {
+                println!("{}", n);
+            }
+            return Ok(());
+        },
+        Some("stats") => {
+            let stats = Statistics::new(&input, turns);
+            print!("{}", stats);
+
+            if let Some(n) = args.parsed_option::<u32>("number")? {
+                match stats.first_occurrence(n) {
+                    Some(t) => println!("{} is first spoken in turn {} and spoken {} times", n, t, stats.frequency(n)),
+                    None => println!("{} is never spoken", n),
+                }
+            }
+            return Ok(());
+        },
+        _ => {},
+    }
+
+    let result = run(&input, turns)?;
+    println!("number spoken in turn {} is {}", turns, result);
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -58,18 +153,58 @@ mod test {
     #[test]
     pub fn example_1_1() {
         let input = vec![0, 3, 6];
-        assert_eq!(436, run(input, 2020).unwrap());
+        assert_eq!(436, run(&input, 2020).unwrap());
     }
 
     #[test]
     pub fn example_1_2() {
         let input = vec![2, 3, 1];
-        assert_eq!(78, run(input, 2020).unwrap());
+        assert_eq!(78, run(&input, 2020).unwrap());
     }
 
     #[test]
     pub fn example_1_3() {
         let input = vec![3,1,2];
-        assert_eq!(1836, run(input, 2020).unwrap());
+        assert_eq!(1836, run(&input, 2020).unwrap());
+    }
+
+    #[test]
+    pub fn example_2_1() {
+        let input = parse_input("0,3,6\n").unwrap();
+        assert_eq!(175594, run(&input, 30_000_000).unwrap());
+    }
+
+    #[test]
+    pub fn game_streams_spoken_numbers() {
+        let input = vec![0, 3, 6];
+        assert_eq!(vec![0, 3, 6, 0, 3, 3, 1, 0, 4, 0], Game::new(&input).take(10).collect::<Vec<_>>());
+        assert_eq!(Some(436), Game::new(&input).nth(2019));
+        assert_eq!(None, Game::new(&[]).next());
+    }
+
+    #[test]
+    pub fn statistics_over_a_game() {
+        let input = vec![0, 3, 6];
+        let stats = Statistics::new(&input, 10);
+
+        assert_eq!(Some(1), stats.first_occurrence(0));
+        assert_eq!(Some(7), stats.first_occurrence(1));
+        assert_eq!(None, stats.first_occurrence(2));
+        assert_eq!(4, stats.frequency(0));
+        assert_eq!(5, stats.distinct());
+        assert_eq!(vec![(0, 4), (3, 3), (1, 1), (4, 1), (6, 1)], stats.histogram());
+        assert_eq!(Some(&stats::Gap { number: 0, from: 4, to: 8 }), stats.longest_gap());
+    }
+
+    #[test]
+    pub fn early_turns_and_large_starting_numbers() {
+        let input = vec![0, 3, 6];
+        assert_eq!(vec![0, 3, 6, 0, 3, 3, 1, 0, 4, 0],
+            (1..=10).map(|t| run(&input, t).unwrap()).collect::<Vec<_>>());
+
+        let input = vec![5000, 5000];
+        assert_eq!(1, run(&input, 3).unwrap());
+        assert!(run(&[], 10).is_err());
+        assert!(run(&input, 0).is_err());
     }
 }
\ No newline at end of file
@@ -0,0 +1,100 @@
+use std::fmt;
+use super::Game;
+
+/// The longest time between two consecutive occurrences of the same number.
+#[derive(Debug, PartialEq)]
+pub struct Gap {
+    pub number: u32,
+    pub from: usize,
+    pub to: usize,
+}
+
+/// Statistics over the first turns of a game. Turns are counted starting at 1.
+pub struct Statistics {
+    turns: usize,
+    first_seen: Vec<usize>,
+    frequency: Vec<usize>,
+    longest_gap: Option<Gap>,
+}
+
+impl Statistics {
+    pub fn new(start: &[u32], turns: usize) -> Statistics {
+        let mut first_seen = vec![];
+        let mut last_seen = vec![];
+        let mut frequency = vec![];
+        let mut longest_gap: Option<Gap> = None;
+
+        for (i, n) in Game::new(start).take(turns).enumerate() {
+            let turn = i + 1;
+            let index = n as usize;
+
+            if index >= first_seen.len() {
+                first_seen.resize(index + 1, 0);
+                last_seen.resize(index + 1, 0);
+                frequency.resize(index + 1, 0);
+            }
+
+            if first_seen[index] == 0 {
+                first_seen[index] = turn;
+            } else {
+                let from = last_seen[index];
+                if longest_gap.as_ref().map(|g| turn - from > g.to - g.from).unwrap_or(true) {
+                    longest_gap = Some(Gap { number: n, from, to: turn });
+                }
+            }
+
+            last_seen[index] = turn;
+            frequency[index] += 1;
+        }
+
+        Statistics { turns, first_seen, frequency, longest_gap }
+    }
+
+    pub fn first_occurrence(&self, n: u32) -> Option<usize> {
+        self.first_seen.get(n as usize).cloned().filter(|t| *t != 0)
+    }
+
+    pub fn frequency(&self, n: u32) -> usize {
+        self.frequency.get(n as usize).cloned().unwrap_or(0)
+    }
+
+    pub fn distinct(&self) -> usize {
+        self.frequency.iter().filter(|f| **f != 0).count()
+    }
+
+    /// All spoken numbers with how often they were spoken, the most frequent
+    /// first.
+    pub fn histogram(&self) -> Vec<(u32, usize)> {
+        let mut histogram = self.frequency.iter()
+            .enumerate()
+            .filter(|(_, f)| **f != 0)
+            .map(|(n, f)| (n as u32, *f))
+            .collect::<Vec<_>>();
+
+        histogram.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        histogram
+    }
+
+    pub fn longest_gap(&self) -> Option<&Gap> {
+        self.longest_gap.as_ref()
+    }
+}
+
+impl fmt::Display for Statistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "turns: {}", self.turns)?;
+        writeln!(f, "distinct numbers: {}", self.distinct())?;
+
+        match &self.longest_gap {
+            Some(g) => writeln!(f, "longest gap: {} turns for {} (turn {} to {})", g.to - g.from, g.number, g.from, g.to)?,
+            None => writeln!(f, "longest gap: none")?,
+        }
+
+        writeln!(f, "most frequent:")?;
+        for (n, count) in self.histogram().iter().take(10) {
+            writeln!(f, "  {}: {} times, first in turn {}", n, count, self.first_occurrence(*n).unwrap())?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,10 +1,14 @@
-use std::hash::Hasher;
-use std::hash::Hash;
 use std::collections::HashSet;
+use num::BigUint;
 use regex::Regex;
 use super::utils::ParseError;
+use super::cli::Args;
 
-#[derive(Debug)]
+mod report;
+
+use report::{Format, Report};
+
+#[derive(Debug, PartialEq, Eq, Hash)]
 struct Range {
     min: u32,
     max: u32,
@@ -16,29 +20,38 @@ impl Range {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, Hash)]
 struct Rule {
     description: String,
     ranges: Vec<Range>,
+    exclusions: Vec<Range>,
 }
 
-impl PartialEq for Rule {
-    fn eq(&self, other: &Self) -> bool {
-        self.description == other.description
+impl Rule {
+    fn is_valid(&self, i: u32) -> bool {
+        self.ranges.iter().any(|r| r.is_valid(i)) &&
+            !self.exclusions.iter().any(|r| r.is_valid(i))
     }
 }
 
-impl Eq for Rule {}
+/// Selects the fields that make up the final product of part 2.
+pub enum FieldSelector {
+    Prefix(String),
+    Regex(Regex),
+}
 
-impl Hash for Rule {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.description.hash(state);
+impl FieldSelector {
+    fn matches(&self, description: &str) -> bool {
+        match self {
+            FieldSelector::Prefix(p) => description.starts_with(p.as_str()),
+            FieldSelector::Regex(re) => re.is_match(description),
+        }
     }
 }
 
-impl Rule {
-    fn is_valid(&self, i: u32) -> bool {
-        self.ranges.iter().any(|r| r.is_valid(i))
+impl Default for FieldSelector {
+    fn default() -> Self {
+        FieldSelector::Prefix("departure".to_string())
     }
 }
 
@@ -51,58 +64,87 @@ struct Puzzle {
     nearby_tickets: Vec<Ticket>,
 }
 
-fn read_rule(s: &str) -> Rule {
-    lazy_static!{
-        static ref RE: Regex = Regex::new(r"^(.+?): (\d+)-(\d+) or (\d+)-(\d+)$").unwrap();
+// a range is either a single value `a`, a closed range `a-b` or an open-ended
+// range `a-` or `-b`.
+fn read_range(s: &str) -> Result<Range, ParseError> {
+    let s = s.trim();
+    let error = || ParseError::new(&format!("Not a valid range: '{}'", s));
+
+    let (min, max) = match s.split_once('-') {
+        Some((min, max)) => (min.trim(), max.trim()),
+        None => (s, s),
+    };
+
+    if min.is_empty() && max.is_empty() {
+        return Err(error());
     }
 
-    let cap = RE.captures(s).unwrap();
-    let description = cap[1].to_string();
+    let min = if min.is_empty() { 0 } else { min.parse::<u32>().map_err(|_| error())? };
+    let max = if max.is_empty() { u32::MAX } else { max.parse::<u32>().map_err(|_| error())? };
 
-    let mut ranges = vec![];
+    if min > max {
+        return Err(error());
+    }
 
-    let min = cap[2].parse::<u32>().unwrap();
-    let max = cap[3].parse::<u32>().unwrap();
-    ranges.push(Range { min, max });
+    Ok(Range { min, max })
+}
 
-    let min = cap[4].parse::<u32>().unwrap();
-    let max = cap[5].parse::<u32>().unwrap();
-    ranges.push(Range { min, max });
+fn read_ranges(s: &str) -> Result<Vec<Range>, ParseError> {
+    s.split(" or ")
+        .map(read_range)
+        .collect::<Result<Vec<_>, ParseError>>()
+}
 
-    Rule { description, ranges }
+// rules have the form `name: <ranges> [except <ranges>]` where `<ranges>` is
+// a list of ranges separated by `or`.
+fn read_rule(s: &str) -> Result<Rule, ParseError> {
+    let (description, definition) = s.split_once(": ")
+        .ok_or_else(|| ParseError::new(&format!("Not a rule: '{}'", s)))?;
+
+    let (ranges, exclusions) = match definition.split_once(" except ") {
+        Some((ranges, exclusions)) => (read_ranges(ranges)?, read_ranges(exclusions)?),
+        None => (read_ranges(definition)?, vec![]),
+    };
+
+    let description = description.trim().to_string();
+    Ok(Rule { description, ranges, exclusions })
 }
 
-fn read_ticket(s: &str) -> Ticket {
+fn read_ticket(s: &str) -> Result<Ticket, ParseError> {
     s.split(',')
         .filter(|v| !v.is_empty())
-        .map(|v| v.parse::<u32>().unwrap())
-        .collect::<Vec<_>>()
+        .map(|v| v.trim().parse::<u32>().map_err(|_| ParseError::new(&format!("Not a valid ticket: '{}'", s))))
+        .collect::<Result<Vec<_>, ParseError>>()
 }
 
-fn parse_input() -> Puzzle {
-    let input = include_str!("./data/input.txt");
+fn parse_input(input: &str) -> Result<Puzzle, ParseError> {
     let puzzle = input
         .split("\n\n")
         .filter(|v| *v != "")
         .collect::<Vec<_>>();
 
+    if puzzle.len() != 3 {
+        return Err(ParseError::new("Expected rules, your ticket and nearby tickets"));
+    }
+
     let rules = puzzle[0].lines()
         .filter(|v| !v.is_empty())
         .map(|l| read_rule(l))
-        .collect::<Vec<_>>();
+        .collect::<Result<Vec<_>, ParseError>>()?;
 
     let my_ticket = puzzle[1].lines()
         .skip(1)
         .map(|t| read_ticket(t))
-        .next().unwrap();
+        .next()
+        .ok_or_else(|| ParseError::new("Your ticket is missing"))??;
 
     let nearby_tickets = puzzle[2].lines()
         .skip(1)
         .filter(|v| !v.is_empty())
         .map(|l| read_ticket(l))
-        .collect::<Vec<_>>();
+        .collect::<Result<Vec<_>, ParseError>>()?;
 
-    Puzzle { rules, my_ticket, nearby_tickets }
+    Ok(Puzzle { rules, my_ticket, nearby_tickets })
 }
 
 fn is_valid_for_some_field(rules: &Vec<Rule>, n: u32) -> bool {
@@ -117,16 +159,16 @@ fn find_invalid_fields(rules: &Vec<Rule>, ticket: &Ticket) -> Vec<u32> {
         .collect::<Vec<_>>()
 }
 
-pub fn problem1() -> Result<(), ParseError> {
-    let input = parse_input();
+fn error_rate(puzzle: &Puzzle) -> u32 {
+    puzzle.nearby_tickets.iter()
+        .flat_map(|t| find_invalid_fields(&puzzle.rules, t))
+        .sum()
+}
 
-    let mut invalid_fields = vec![];
-    for t in &input.nearby_tickets {
-        let mut invalid_fields_for_ticket = find_invalid_fields(&input.rules, t);
-        invalid_fields.append(&mut invalid_fields_for_ticket);
-    }
+pub fn problem1() -> Result<(), ParseError> {
+    let input = parse_input(include_str!("./data/input.txt"))?;
 
-    let result: u32 = invalid_fields.iter().sum();
+    let result = error_rate(&input);
     println!("16/1: sum of invalid fields is {}", result);
 
     Ok(())
@@ -143,35 +185,23 @@ fn determine_valid_rules(rules: &Vec<Rule>, n: u32) -> HashSet<&Rule> {
         .collect::<HashSet<_>>()
 }
 
-fn determine_valid_rules_2(rules: &Vec<Rule>, n: u32) -> HashSet<usize> {
-    rules.iter()
-        .enumerate()
-        .filter(|(_, r)| r.is_valid(n))
-        .map(|(i, _)| i)
-        .collect::<HashSet<_>>()
-}
-
-pub fn problem2() -> Result<(), ParseError> {
-    let input = parse_input();
-
-    let valid_nearby_tickets = input.nearby_tickets.iter()
-        .filter(|t| is_valid(&input.rules, t))
+// returns the rule for each column of the tickets, ordered by column
+fn determine_columns(puzzle: &Puzzle) -> Result<Vec<(usize, &Rule)>, ParseError> {
+    let valid_nearby_tickets = puzzle.nearby_tickets.iter()
+        .filter(|t| is_valid(&puzzle.rules, t))
         .collect::<Vec<_>>();
 
-    let len = input.rules.len();
+    let len = puzzle.my_ticket.len();
     let mut candidates = vec![];
 
     for i in 0..len {
+        let mut one_rule = puzzle.rules.iter().collect::<HashSet<_>>();
 
-        let valid_rules = valid_nearby_tickets.iter()
-            .map(|t| determine_valid_rules(&input.rules, t[i]))
-            .collect::<Vec<_>>();
-
-        let mut rules_iter = valid_rules.iter();
-        let mut one_rule = rules_iter.next().cloned().unwrap();
-
-        for r in rules_iter {
-            one_rule = one_rule.intersection(&r).cloned().collect();
+        for t in &valid_nearby_tickets {
+            let value = *t.get(i)
+                .ok_or_else(|| ParseError::new(&format!("Ticket {:?} has no column {}", t, i)))?;
+            let valid_rules = determine_valid_rules(&puzzle.rules, value);
+            one_rule = one_rule.intersection(&valid_rules).cloned().collect();
         }
 
         candidates.push((i, one_rule));
@@ -187,21 +217,141 @@ pub fn problem2() -> Result<(), ParseError> {
         }
 
         if c.1.len() != 1 {
-            panic!("could not uniquely determine which column corresponds to which field");
+            return Err(ParseError::new(&format!("Could not uniquely determine the field of column {}", c.0)));
         }
 
-        let next_column = c.1.iter().next().unwrap();
+        let next_column = *c.1.iter().next().unwrap();
         columns.push((c.0, next_column));
-        already_assigned.insert(*next_column);
+        already_assigned.insert(next_column);
     }
 
-    let readable_candidates = columns.iter().map(|(i, c)| (i, c.description.clone())).collect::<Vec<_>>();
+    columns.sort_by_key(|(i, _)| *i);
+    Ok(columns)
+}
+
+// a selector can pick all twenty fields, their product does not fit into a u64
+fn departure_product(puzzle: &Puzzle, selector: &FieldSelector) -> Result<BigUint, ParseError> {
+    let columns = determine_columns(puzzle)?;
 
-    let result: u64 = readable_candidates.iter()
-        .filter(|(_, d)| d.contains("departure"))
-        .map(|(i, _)| input.my_ticket[**i] as u64)
+    let result = columns.iter()
+        .filter(|(_, r)| selector.matches(&r.description))
+        .map(|(i, _)| BigUint::from(puzzle.my_ticket[*i]))
         .product();
-    println!("16/2: sum of invalid fields is {}", result);
+
+    Ok(result)
+}
+
+pub fn problem2() -> Result<(), ParseError> {
+    let input = parse_input(include_str!("./data/input.txt"))?;
+
+    let result = departure_product(&input, &FieldSelector::default())?;
+    println!("16/2: product of departure fields is {}", result);
 
     Ok(())
 }
+
+fn field_selector(args: &Args) -> Result<FieldSelector, ParseError> {
+    match (args.option("prefix"), args.option("regex")) {
+        (Some(_), Some(_)) => Err(ParseError::new("Use either --prefix or --regex, not both")),
+        (Some(prefix), None) => Ok(FieldSelector::Prefix(prefix.to_string())),
+        (None, Some(regex)) => Ok(FieldSelector::Regex(Regex::new(regex)?)),
+        (None, None) => Ok(FieldSelector::default()),
+    }
+}
+
+pub fn command(args: &Args) -> Result<(), ParseError> {
+    let input = args.input(include_str!("./data/input.txt"))?;
+    let puzzle = parse_input(&input)?;
+
+    if args.positional(1) == Some("report") {
+        let format = args.parsed_option::<Format>("format")?.unwrap_or(Format::Text);
+        print!("{}", Report::new(&puzzle).render(&format));
+        return Ok(());
+    }
+
+    let selector = field_selector(args)?;
+
+    println!("16/1: sum of invalid fields is {}", error_rate(&puzzle));
+    println!("16/2: product of selected fields is {}", departure_product(&puzzle, &selector)?);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn example_1_1() {
+        let puzzle = parse_input(include_str!("./data/example.txt")).unwrap();
+        assert_eq!(71, error_rate(&puzzle));
+    }
+
+    #[test]
+    pub fn example_2_1() {
+        let puzzle = parse_input(include_str!("./data/example2.txt")).unwrap();
+        let columns = determine_columns(&puzzle).unwrap()
+            .into_iter()
+            .map(|(_, r)| r.description.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(vec!["row", "class", "seat"], columns);
+
+        let selector = FieldSelector::Regex(Regex::new("^(class|seat)$").unwrap());
+        assert_eq!(BigUint::from(12u32 * 13), departure_product(&puzzle, &selector).unwrap());
+    }
+
+    #[test]
+    pub fn all_fields_can_be_selected() {
+        let selector = FieldSelector::Regex(Regex::new(".").unwrap());
+
+        let puzzle = parse_input(include_str!("./data/example2.txt")).unwrap();
+        assert_eq!(BigUint::from(11u32 * 12 * 13), departure_product(&puzzle, &selector).unwrap());
+
+        let puzzle = parse_input(include_str!("./data/input.txt")).unwrap();
+        let expected = puzzle.my_ticket.iter().map(|&v| BigUint::from(v)).product::<BigUint>();
+        assert!(expected > BigUint::from(u64::MAX));
+        assert_eq!(expected, departure_product(&puzzle, &selector).unwrap());
+    }
+
+    #[test]
+    pub fn report_lists_invalid_values_and_columns() {
+        let puzzle = parse_input(include_str!("./data/example.txt")).unwrap();
+        let csv = Report::new(&puzzle).render(&Format::Csv);
+        let lines = csv.lines().collect::<Vec<_>>();
+
+        assert_eq!("record,ticket,column,value,status,fields", lines[0]);
+        assert_eq!("value,1,0,7,valid,class;row", lines[1]);
+        assert_eq!("value,2,1,4,invalid,", lines[5]);
+        assert_eq!(1 + 4 * 3 + 3, lines.len());
+
+        let text = Report::new(&puzzle).render(&Format::Text);
+        assert!(text.contains("ticket 2: 1 invalid value(s)"));
+        assert!(text.contains("error rate: 71"));
+    }
+
+    #[test]
+    pub fn rules_support_open_ranges_and_exclusions() {
+        let rule = read_rule("age: 5 or 10- except 12-14 or 20").unwrap();
+
+        assert!(rule.is_valid(5));
+        assert!(!rule.is_valid(6));
+        assert!(rule.is_valid(11));
+        assert!(!rule.is_valid(13));
+        assert!(!rule.is_valid(20));
+        assert!(rule.is_valid(1_000_000));
+
+        let rule = read_rule("low: -3 or 7-8 or 9-9").unwrap();
+        assert!(rule.is_valid(0));
+        assert!(!rule.is_valid(4));
+        assert!(rule.is_valid(9));
+    }
+
+    #[test]
+    pub fn invalid_rules_are_rejected() {
+        assert!(read_rule("class 1-3").is_err());
+        assert!(read_rule("class: 3-1").is_err());
+        assert!(read_rule("class: -").is_err());
+        assert!(read_rule("class: 1-3 or x").is_err());
+    }
+}
@@ -0,0 +1,154 @@
+use std::fmt::Write;
+use std::str::FromStr;
+use super::super::utils::ParseError;
+use super::{Puzzle, Rule, determine_columns};
+
+pub enum Format {
+    Text,
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Format, ParseError> {
+        match s {
+            "text" => Ok(Format::Text),
+            "csv" => Ok(Format::Csv),
+            _ => Err(ParseError::new(&format!("Unknown report format: {}", s))),
+        }
+    }
+}
+
+struct Value<'a> {
+    value: u32,
+    rules: Vec<&'a Rule>,
+}
+
+impl<'a> Value<'a> {
+    fn is_valid(&self) -> bool {
+        !self.rules.is_empty()
+    }
+
+    fn fields(&self) -> String {
+        self.rules.iter()
+            .map(|r| r.description.as_str())
+            .collect::<Vec<_>>()
+            .join(";")
+    }
+}
+
+struct TicketReport<'a> {
+    values: Vec<Value<'a>>,
+}
+
+struct Column<'a> {
+    column: usize,
+    rule: &'a Rule,
+    value: u32,
+}
+
+pub struct Report<'a> {
+    tickets: Vec<TicketReport<'a>>,
+    columns: Result<Vec<Column<'a>>, ParseError>,
+}
+
+impl<'a> Report<'a> {
+    pub fn new(puzzle: &'a Puzzle) -> Report<'a> {
+        let tickets = puzzle.nearby_tickets.iter()
+            .map(|t| {
+                let values = t.iter()
+                    .map(|&value| Value {
+                        value,
+                        rules: puzzle.rules.iter().filter(|r| r.is_valid(value)).collect(),
+                    })
+                    .collect();
+                TicketReport { values }
+            })
+            .collect();
+
+        let columns = determine_columns(puzzle)
+            .map(|columns| columns.into_iter()
+                .map(|(column, rule)| Column { column, rule, value: puzzle.my_ticket[column] })
+                .collect());
+
+        Report { tickets, columns }
+    }
+
+    pub fn render(&self, format: &Format) -> String {
+        match format {
+            Format::Text => self.text(),
+            Format::Csv => self.csv(),
+        }
+    }
+
+    fn text(&self) -> String {
+        let mut out = String::new();
+
+        for (i, t) in self.tickets.iter().enumerate() {
+            let invalid = t.values.iter().filter(|v| !v.is_valid()).count();
+            let status = if invalid == 0 { "valid".to_string() } else { format!("{} invalid value(s)", invalid) };
+            writeln!(out, "ticket {}: {}", i + 1, status).unwrap();
+
+            for (column, v) in t.values.iter().enumerate() {
+                if v.is_valid() {
+                    writeln!(out, "  [{}] {}: {}", column, v.value, v.fields().replace(';', ", ")).unwrap();
+                } else {
+                    writeln!(out, "  [{}] {}: INVALID", column, v.value).unwrap();
+                }
+            }
+        }
+
+        let error_rate: u32 = self.tickets.iter()
+            .flat_map(|t| t.values.iter())
+            .filter(|v| !v.is_valid())
+            .map(|v| v.value)
+            .sum();
+        writeln!(out, "error rate: {}", error_rate).unwrap();
+
+        writeln!(out, "columns:").unwrap();
+        match &self.columns {
+            Ok(columns) => {
+                for c in columns {
+                    writeln!(out, "  [{}] {}: {}", c.column, c.rule.description, c.value).unwrap();
+                }
+            },
+            Err(e) => writeln!(out, "  {}", e).unwrap(),
+        }
+
+        out
+    }
+
+    // tickets and the column mapping are written as one table, the first
+    // field tells which kind of record a row is.
+    fn csv(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "record,ticket,column,value,status,fields").unwrap();
+
+        for (i, t) in self.tickets.iter().enumerate() {
+            for (column, v) in t.values.iter().enumerate() {
+                let status = if v.is_valid() { "valid" } else { "invalid" };
+                writeln!(out, "value,{},{},{},{},{}", i + 1, column, v.value, status, escape(&v.fields())).unwrap();
+            }
+        }
+
+        match &self.columns {
+            Ok(columns) => {
+                for c in columns {
+                    writeln!(out, "column,,{},{},mapped,{}", c.column, c.value, escape(&c.rule.description)).unwrap();
+                }
+            },
+            Err(e) => writeln!(out, "column,,,,error,{}", escape(&e.to_string())).unwrap(),
+        }
+
+        out
+    }
+}
+
+fn escape(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use super::Food;
+use super::solver::Solution;
+
+// a food that removed candidates of an allergen, foods are numbered by their
+// position in the input starting with 1
+struct Narrowing {
+    food: usize,
+    remaining: usize,
+}
+
+struct Deduction<'a> {
+    allergen: &'a str,
+    ingredient: &'a str,
+    narrowed_by: Vec<Narrowing>,
+    candidates: BTreeSet<&'a str>,
+    step: Option<usize>,
+}
+
+/// How each allergen was tied to its ingredient.
+pub struct Derivation<'a> {
+    deductions: Vec<Deduction<'a>>,
+    solution: &'a Solution<'a>,
+}
+
+impl<'a> Derivation<'a> {
+    pub fn new(food: &'a [Food], solution: &'a Solution<'a>) -> Derivation<'a> {
+        let deductions = solution.dangerous.iter()
+            .map(|(&allergen, &ingredient)| {
+                let mut candidates: Option<BTreeSet<&str>> = None;
+                let mut narrowed_by = vec![];
+
+                for (i, f) in food.iter().enumerate() {
+                    if !f.allergens.iter().any(|a| a == allergen) {
+                        continue;
+                    }
+
+                    let ingredients = f.ingredients.iter().map(|i| i.as_str()).collect::<BTreeSet<_>>();
+                    let before = candidates.as_ref().map(|c| c.len());
+                    let after = match candidates {
+                        Some(c) => c.intersection(&ingredients).cloned().collect(),
+                        None => ingredients,
+                    };
+
+                    if before != Some(after.len()) {
+                        narrowed_by.push(Narrowing { food: i + 1, remaining: after.len() });
+                    }
+                    candidates = Some(after);
+                }
+
+                Deduction {
+                    allergen,
+                    ingredient,
+                    narrowed_by,
+                    candidates: candidates.unwrap_or_default(),
+                    step: solution.steps.iter().position(|a| *a == allergen).map(|s| s + 1),
+                }
+            })
+            .collect();
+
+        Derivation { deductions, solution }
+    }
+
+    // ingredients of allergens fixed before the given step
+    fn fixed_before(&self, step: usize) -> BTreeSet<&'a str> {
+        self.solution.steps[..step - 1].iter()
+            .map(|a| self.solution.dangerous[a])
+            .collect()
+    }
+}
+
+impl<'a> fmt::Display for Derivation<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for d in &self.deductions {
+            writeln!(f, "{}: {}", d.allergen, d.ingredient)?;
+
+            for n in &d.narrowed_by {
+                writeln!(f, "  food {} leaves {} candidate(s)", n.food, n.remaining)?;
+            }
+
+            let candidates = d.candidates.iter().cloned().collect::<Vec<_>>();
+            writeln!(f, "  candidates: {}", candidates.join(", "))?;
+
+            match d.step {
+                Some(step) => {
+                    let removed = self.fixed_before(step)
+                        .intersection(&d.candidates)
+                        .cloned()
+                        .collect::<Vec<_>>();
+
+                    if removed.is_empty() {
+                        writeln!(f, "  fixed in step {}", step)?;
+                    } else {
+                        writeln!(f, "  fixed in step {} after removing {}", step, removed.join(", "))?;
+                    }
+                },
+                None => writeln!(f, "  fixed by matching the remaining candidates")?,
+            }
+        }
+
+        let safe = self.solution.safe.iter().cloned().collect::<Vec<_>>();
+        writeln!(f, "safe ingredients: {}", safe.join(", "))?;
+        writeln!(f, "canonical dangerous ingredient list: {}", self.solution.dangerous_list())
+    }
+}
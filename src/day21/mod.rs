@@ -1,8 +1,13 @@
-use std::collections::HashSet;
-use std::collections::HashMap;
 use std::str::FromStr;
 use regex::Regex;
 use super::utils::ParseError;
+use super::cli::Args;
+
+mod solver;
+mod explain;
+
+use solver::solve;
+use explain::Derivation;
 
 #[derive(Debug)]
 struct Food {
@@ -26,8 +31,7 @@ impl FromStr for Food{
     }
 }
 
-fn parse_input() -> Result<Vec<Food>, ParseError> {
-    let input = include_str!("./data/input.txt");
+fn parse_input(input: &str) -> Result<Vec<Food>, ParseError> {
     input
         .lines()
         .filter(|v| *v != "")
@@ -35,97 +39,86 @@ fn parse_input() -> Result<Vec<Food>, ParseError> {
         .collect::<Result<Vec<_>, ParseError>>()
 }
 
-fn map_allergens_to_food(food: &Vec<Food>) -> (HashMap<&str, HashSet<&str>>, HashSet<&str>) {
-    let mut all_ingredients = HashSet::new();
-    let mut map: HashMap<&str, Vec<HashSet<&str>>> = HashMap::new();
-    for f in food {
-        for a in &f.allergens {
-            for i in &f.ingredients {
-                all_ingredients.insert(i.as_str());
-            }
-            map.entry(&a)
-                .and_modify(|l| l.push(f.ingredients.iter().map(|v| v.as_str()).collect::<HashSet<_>>()))
-                .or_insert(vec![f.ingredients.iter().map(|v| v.as_str()).collect::<HashSet<_>>()]);
-        }
-    }
+pub fn problem1() -> Result<(), ParseError> {
+    let food = parse_input(include_str!("./data/input.txt"))?;
+    let solution = solve(&food)?;
 
-    let mut allergen_to_food_map: HashMap<&str, HashSet<&str>> = HashMap::new();
-    for (allergen, ingredient_lists) in &map {
-        let mut result = all_ingredients.clone();
-        for v in ingredient_lists.iter() {
-            result = result.intersection(v).cloned().collect::<HashSet<_>>();
-        }
-        allergen_to_food_map.entry(allergen).or_insert(result);
-    }
+    println!("21/1: # of times all non-allergenic food appears: {}", solution.safe_appearances(&food));
 
-    (allergen_to_food_map, all_ingredients)
+    Ok(())
 }
 
-pub fn problem1() -> Result<(), ParseError> {
-    let food = parse_input()?;
+pub fn problem2() -> Result<(), ParseError> {
+    let food = parse_input(include_str!("./data/input.txt"))?;
+    let solution = solve(&food)?;
 
-    let (allergen_to_food_map, all_ingredients) = map_allergens_to_food(&food);
+    println!("21/2: Canonical dangerous ingredient list: {}", solution.dangerous_list());
 
-    let mut allergenic_ingredients = HashSet::new();
-    for (_, ingredients) in &allergen_to_food_map {
-        for ingredient in ingredients {
-            allergenic_ingredients.insert(ingredient);
-        }
-    }
+    Ok(())
+}
 
-    let mut non_allergenic_ingredients = all_ingredients.clone();
-    for ingredient in allergenic_ingredients {
-        non_allergenic_ingredients.remove(ingredient);
-    }
+pub fn command(args: &Args) -> Result<(), ParseError> {
+    let food = parse_input(&args.input(include_str!("./data/input.txt"))?)?;
+    let solution = solve(&food)?;
 
-    let result = food.iter()
-        .map(|f| f.ingredients.iter().filter(|i| non_allergenic_ingredients.contains(i.as_str())))
-        .flatten()
-        .count();
+    if args.flag("explain") {
+        print!("{}", Derivation::new(&food, &solution));
+        return Ok(());
+    }
 
-    println!("21/1: # of times all non-allergenic food appears: {}", result);
+    println!("# of times all non-allergenic food appears: {}", solution.safe_appearances(&food));
+    println!("canonical dangerous ingredient list: {}", solution.dangerous_list());
 
     Ok(())
 }
 
-pub fn problem2() -> Result<(), ParseError> {
-    let food = parse_input()?;
-    let (allergen_to_food_map, _) = map_allergens_to_food(&food);
-
-    let mut a_to_i = allergen_to_food_map.iter()
-        .map(|(a, is)| (a, is.clone()))
-        .collect::<Vec<_>>();
-    a_to_i.sort_by(|a, b| a.1.len().cmp(&b.1.len()));
-
-    let mut already_assigned: HashSet<&str> = HashSet::new();
-    let mut max = 2;
-    while max > 1 {
-        for i in 0..a_to_i.len() {
-            if a_to_i[i].1.len() == 1 {
-                already_assigned.insert(a_to_i[i].1.iter().next().unwrap());
-                continue;
-            }
-
-            for a in &already_assigned {
-                a_to_i[i].1.remove(a);
-            }
-
-            if a_to_i[i].1.len() > 1 {
-                continue;
-            }
-
-            already_assigned.insert(a_to_i[i].1.iter().next().unwrap());
-        }
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn example_1_1() {
+        let food = parse_input(include_str!("./data/example.txt")).unwrap();
+        let solution = solve(&food).unwrap();
 
-        max = a_to_i.iter().map(|v| v.1.len()).max().unwrap();
+        assert_eq!(5, solution.safe_appearances(&food));
+        assert_eq!("mxmxvkd,sqjhc,fvjkl", solution.dangerous_list());
     }
 
-    a_to_i.sort_by(|a, b| a.0.cmp(&b.0));
-    print!("21/2: Canonical dangerous ingredient list: ");
-    for i in a_to_i {
-        print!("{},", i.1.iter().next().unwrap());
+    #[test]
+    pub fn deductions_are_explained() {
+        let food = parse_input(include_str!("./data/example.txt")).unwrap();
+        let solution = solve(&food).unwrap();
+        let explanation = Derivation::new(&food, &solution).to_string();
+
+        assert_eq!(vec!["dairy", "fish", "soy"], solution.steps);
+        assert!(explanation.contains("dairy: mxmxvkd\n  food 1 leaves 4 candidate(s)\n  food 2 leaves 1 candidate(s)\n"));
+        assert!(explanation.contains("fish: sqjhc\n  food 1 leaves 4 candidate(s)\n  food 4 leaves 2 candidate(s)\n  candidates: mxmxvkd, sqjhc\n  fixed in step 2 after removing mxmxvkd\n"));
+        assert!(explanation.contains("safe ingredients: kfcds, nhms, sbzzf, trh\n"));
+        assert!(explanation.ends_with("canonical dangerous ingredient list: mxmxvkd,sqjhc,fvjkl\n"));
     }
-    println!("");
 
-    Ok(())
+    #[test]
+    pub fn unsolvable_input_is_reported() {
+        let ambiguous = "a b (contains dairy)\na b (contains fish)\na b c (contains soy)";
+        let food = parse_input(ambiguous).unwrap();
+        let error = solve(&food).err().unwrap();
+        assert_eq!("Ambiguous allergen information, undetermined allergens: dairy, fish", error.to_string());
+
+        let crowded = "a b c d e f g h i j k l (contains a0, a1, a2, a3, a4, a5, a6, a7, a8, a9)";
+        let food = parse_input(crowded).unwrap();
+        let error = solve(&food).err().unwrap();
+        assert_eq!("Ambiguous allergen information, undetermined allergens: a0, a1, a2, a3, a4, a5, a6, a7, a8, a9",
+            error.to_string());
+
+        let contradictory = "a b (contains dairy)\nc (contains dairy, fish)";
+        let food = parse_input(contradictory).unwrap();
+        let error = solve(&food).err().unwrap();
+        assert_eq!("Contradictory allergen information, no ingredient left for: dairy", error.to_string());
+
+        let pigeonhole = "i0 i1 i2 i3 i4 i5 i6 i7 i8 i9 i10 (contains a00, a01, a02, a03, a04, a05, a06, a07, a08, a09, a10, a11)";
+        let food = parse_input(pigeonhole).unwrap();
+        let error = solve(&food).err().unwrap();
+        assert!(error.to_string().starts_with("Contradictory allergen information, no ingredient left for: a"));
+    }
 }
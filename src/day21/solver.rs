@@ -0,0 +1,194 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use pathfinding::directed::strongly_connected_components::strongly_connected_components;
+use pathfinding::kuhn_munkres::kuhn_munkres;
+use pathfinding::matrix::Matrix;
+use super::super::utils::ParseError;
+use super::Food;
+
+/// The ingredients that may contain each allergen.
+pub type Candidates<'a> = BTreeMap<&'a str, BTreeSet<&'a str>>;
+
+type Assignment<'a> = BTreeMap<&'a str, &'a str>;
+
+/// The ingredient each allergen is found in and the ingredients that are
+/// proven to contain no allergen at all.
+pub struct Solution<'a> {
+    pub dangerous: Assignment<'a>,
+    pub safe: BTreeSet<&'a str>,
+    // allergens in the order they were fixed by elimination, the others
+    // were determined by matching the remaining candidates
+    pub steps: Vec<&'a str>,
+}
+
+impl<'a> Solution<'a> {
+    pub fn safe_appearances(&self, food: &[Food]) -> usize {
+        food.iter()
+            .flat_map(|f| f.ingredients.iter())
+            .filter(|i| self.safe.contains(i.as_str()))
+            .count()
+    }
+
+    /// The dangerous ingredients sorted by their allergen.
+    pub fn dangerous_list(&self) -> String {
+        self.dangerous.values()
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// An allergen can only be contained in ingredients that are listed in
+/// every food the allergen is declared for.
+pub fn candidates(food: &[Food]) -> Candidates<'_> {
+    let mut candidates: Candidates = BTreeMap::new();
+
+    for f in food {
+        let ingredients = f.ingredients.iter().map(|i| i.as_str()).collect::<BTreeSet<_>>();
+
+        for a in &f.allergens {
+            candidates.entry(a.as_str())
+                .and_modify(|c| c.retain(|i| ingredients.contains(i)))
+                .or_insert_with(|| ingredients.clone());
+        }
+    }
+
+    candidates
+}
+
+// assigns allergens that have only one candidate left until there are none
+// left, returns the assigned allergens in order and the candidates of the
+// allergens that could not be assigned this way
+fn eliminate<'a>(candidates: &Candidates<'a>) -> (Vec<&'a str>, Assignment<'a>, Candidates<'a>) {
+    let mut candidates = candidates.clone();
+    let mut steps = vec![];
+    let mut assigned = BTreeMap::new();
+
+    while let Some((&allergen, ingredients)) = candidates.iter().find(|(_, c)| c.len() == 1) {
+        let ingredient = *ingredients.iter().next().unwrap();
+        steps.push(allergen);
+        assigned.insert(allergen, ingredient);
+        candidates.remove(allergen);
+
+        for c in candidates.values_mut() {
+            c.remove(ingredient);
+        }
+    }
+
+    (steps, assigned, candidates)
+}
+
+// assigns the allergens to distinct ingredients so that as many allergens
+// as possible get one of their candidates. allergens without a candidate in
+// the result could not be matched at all.
+fn maximum_matching<'a>(candidates: &Candidates<'a>) -> (Assignment<'a>, Vec<&'a str>) {
+    let allergens = candidates.keys().cloned().collect::<Vec<_>>();
+    let ingredients = candidates.values()
+        .flatten()
+        .cloned()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    // kuhn_munkres needs at least as many columns as rows, the extra columns
+    // are never candidates
+    let columns = ingredients.len().max(allergens.len());
+    let mut weights = Matrix::new(allergens.len(), columns, 0i64);
+    for (row, a) in allergens.iter().enumerate() {
+        for (column, i) in ingredients.iter().enumerate() {
+            if candidates[a].contains(i) {
+                weights[&(row, column)] = 1;
+            }
+        }
+    }
+
+    let (_, columns) = kuhn_munkres(&weights);
+
+    let mut matched = BTreeMap::new();
+    let mut unmatched = vec![];
+    for (a, column) in allergens.into_iter().zip(columns) {
+        match ingredients.get(column).filter(|i| candidates[a].contains(*i)) {
+            Some(i) => { matched.insert(a, *i); },
+            None => unmatched.push(a),
+        }
+    }
+
+    (matched, unmatched)
+}
+
+// An allergen can swap its ingredient if there is an alternating path that
+// starts with one of its other candidates and ends either in an ingredient
+// nobody is matched to or back at the allergen itself.
+fn undetermined<'a>(candidates: &Candidates<'a>, matched: &Assignment<'a>) -> BTreeSet<&'a str> {
+    let owner = matched.iter()
+        .map(|(&a, &i)| (i, a))
+        .collect::<BTreeMap<_, _>>();
+    let others = |a: &&'a str| candidates[a].iter()
+        .filter(|i| matched[a] != **i)
+        .cloned()
+        .collect::<Vec<_>>();
+    let successors = |a: &&'a str| others(a).iter()
+        .filter_map(|i| owner.get(i).cloned())
+        .collect::<Vec<_>>();
+
+    let allergens = matched.keys().cloned().collect::<Vec<_>>();
+    let mut result = strongly_connected_components(&allergens, successors)
+        .into_iter()
+        .filter(|c| c.len() > 1)
+        .flatten()
+        .collect::<BTreeSet<_>>();
+
+    // walk the alternating paths backwards from the allergens that have a
+    // free ingredient among their candidates
+    let mut predecessors: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for a in &allergens {
+        for b in successors(a) {
+            predecessors.entry(b).or_default().push(a);
+        }
+    }
+
+    let mut queue = allergens.iter()
+        .filter(|a| others(a).iter().any(|i| !owner.contains_key(i)))
+        .cloned()
+        .collect::<VecDeque<_>>();
+    let mut reaches_free = queue.iter().cloned().collect::<BTreeSet<_>>();
+    while let Some(a) = queue.pop_front() {
+        for p in predecessors.get(a).into_iter().flatten() {
+            if reaches_free.insert(p) {
+                queue.push_back(p);
+            }
+        }
+    }
+
+    result.append(&mut reaches_free);
+    result
+}
+
+/// Determines which ingredient contains which allergen. Fails if the foods
+/// allow no assignment at all or more than one.
+pub fn solve(food: &[Food]) -> Result<Solution<'_>, ParseError> {
+    let (steps, mut dangerous, open) = eliminate(&candidates(food));
+    let (mut matched, unmatched) = maximum_matching(&open);
+
+    if !unmatched.is_empty() {
+        return Err(ParseError::new(&format!(
+            "Contradictory allergen information, no ingredient left for: {}",
+            unmatched.join(", "))));
+    }
+
+    let undetermined = undetermined(&open, &matched);
+    if !undetermined.is_empty() {
+        let undetermined = undetermined.into_iter().collect::<Vec<_>>();
+        return Err(ParseError::new(&format!(
+            "Ambiguous allergen information, undetermined allergens: {}",
+            undetermined.join(", "))));
+    }
+
+    dangerous.append(&mut matched);
+    let safe = food.iter()
+        .flat_map(|f| f.ingredients.iter())
+        .map(|i| i.as_str())
+        .filter(|i| !dangerous.values().any(|d| d == i))
+        .collect();
+
+    Ok(Solution { dangerous, safe, steps })
+}
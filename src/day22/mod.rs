@@ -1,131 +1,571 @@
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
+use std::fmt::Write;
+use itertools::join;
 use super::utils::ParseError;
+use super::cli::Args;
+
+mod rules;
+mod simulate;
+
+use rules::{Rules, Recursion, TieBreak, Trigger};
+use simulate::Simulation;
 
 type Deck = VecDeque<u64>;
 
-#[derive(Clone)]
 struct Game {
     player: Vec<Deck>,
     winner: Option<usize>,
 }
 
-fn parse_deck(s: &str) -> Deck {
+fn parse_deck(s: &str) -> Result<Deck, ParseError> {
     s.lines()
         .skip(1)
-        .map(|l| l.parse::<u64>().unwrap())
+        .filter(|l| !l.is_empty())
+        .map(|l| l.parse::<u64>().map_err(ParseError::from))
         .collect()
 }
 
-fn parse_input() -> Vec<Deck> {
-    let input = include_str!("./data/input.txt");
+fn parse_input(input: &str) -> Result<Vec<Deck>, ParseError> {
     input
         .split("\n\n")
         .filter(|v| *v != "")
         .map(|v| parse_deck(v))
-        .collect::<Vec<_>>()
+        .collect::<Result<Vec<_>, ParseError>>()
+}
+
+// appends a line to the transcript if the game is logged
+macro_rules! log {
+    ($combat:expr, $($arg:tt)*) => {
+        if let Some(log) = $combat.log.as_mut() {
+            writeln!(log, $($arg)*).unwrap();
+        }
+    };
 }
 
-fn game_state_existed_before(previous_games: &Vec<Game>, current: &Game) -> bool {
-    previous_games.iter()
-        .any(|g| g.player[0] == current.player[0] && g.player[1] == current.player[1])
+/// Plays Combat with any number of players under the given rules and
+/// optionally keeps a transcript of every round in the same format as the
+/// puzzle description.
+struct Combat {
+    rules: Rules,
+    log: Option<String>,
+    games: usize,
+    // rounds played in the outermost game
+    rounds: usize,
+    // number of games played at each depth, the outermost game has depth 0
+    depths: Vec<usize>,
+    // how often a game ended because a state repeated
+    repetitions: usize,
 }
 
-fn turn(mut game: Game, previous_turns: &mut Vec<Game>, recurse: bool, level: usize) -> Game {
-    if game_state_existed_before(&previous_turns, &game) {
-        game.winner = Some(0);
-        return game;
+impl Combat {
+    fn new(rules: Rules, logging: bool) -> Combat {
+        let log = if logging { Some(String::new()) } else { None };
+        Combat { rules, log, games: 0, rounds: 0, depths: vec![], repetitions: 0 }
     }
 
-    let t1 = game.player[0].pop_front();
-    let t2 = game.player[1].pop_front();
+    fn play(&mut self, decks: Vec<Deck>) -> Game {
+        let mut game = Game { player: decks, winner: None };
+        self.play_game(&mut game, 0);
+
+        if self.rules.is_recursive() {
+            log!(self, "");
+        }
+        log!(self, "== Post-game results ==");
+        for (i, deck) in game.player.iter().enumerate() {
+            log!(self, "Player {}'s deck: {}", i + 1, join(deck, ", "));
+        }
 
-    if t1.is_none() {
-        game.player[1].push_front(t2.unwrap());
-        game.winner = Some(1);
-        return game;
+        game
     }
 
-    if t2.is_none() {
-        game.player[0].push_front(t1.unwrap());
-        game.winner = Some(0);
-        return game;
+    fn play_game(&mut self, game: &mut Game, depth: usize) {
+        self.games += 1;
+        let id = self.games;
+        let recursive = self.rules.is_recursive();
+
+        if self.depths.len() <= depth {
+            self.depths.resize(depth + 1, 0);
+        }
+        self.depths[depth] += 1;
+
+        if recursive {
+            log!(self, "=== Game {} ===\n", id);
+        }
+
+        // every state seen in this game, a state that repeats ends the game
+        // in favor of the first player who still has cards
+        let mut seen: HashSet<Vec<Deck>> = HashSet::new();
+        let mut round = 0;
+
+        // rounds are only played while at least two players have cards, if a
+        // tie discarded the last cards the game ends without a winner
+        game.winner = winner_by_cards(game);
+        while game.winner.is_none() && players_with_cards(game) > 1 {
+            if recursive && !seen.insert(game.player.clone()) {
+                let winner = match game.player.iter().position(|d| !d.is_empty()) {
+                    Some(w) => w,
+                    None => break,
+                };
+                log!(self, "-- Round {} (Game {}) --", round + 1, id);
+                log!(self, "This state was seen before, player {} wins game {}!\n", winner + 1, id);
+                game.winner = Some(winner);
+                self.repetitions += 1;
+                break;
+            }
+
+            if self.rules.max_rounds.map(|max| round >= max).unwrap_or(false) {
+                break;
+            }
+
+            round += 1;
+            self.round(game, id, round, depth);
+            game.winner = winner_by_cards(game);
+        }
+
+        if depth == 0 {
+            self.rounds = round;
+        }
+
+        if recursive {
+            if let Some(winner) = game.winner {
+                log!(self, "The winner of game {} is player {}!\n", id, winner + 1);
+            } else {
+                log!(self, "Game {} ends without a winner.\n", id);
+            }
+        }
     }
 
-    let t1 = t1.unwrap();
-    let t2 = t2.unwrap();
-    let mut winner = 0;
+    fn round(&mut self, game: &mut Game, id: usize, round: usize, depth: usize) {
+        if self.rules.is_recursive() {
+            log!(self, "-- Round {} (Game {}) --", round, id);
+        } else {
+            log!(self, "-- Round {} --", round);
+        }
+
+        for (i, deck) in game.player.iter().enumerate() {
+            log!(self, "Player {}'s deck: {}", i + 1, join(deck, ", "));
+        }
+
+        // players without cards are out of the game
+        let active = (0..game.player.len())
+            .filter(|i| !game.player[*i].is_empty())
+            .collect::<Vec<_>>();
+        let cards = active.iter()
+            .map(|i| game.player[*i].pop_front().unwrap())
+            .collect::<Vec<_>>();
 
-    // determine sub game
-    if recurse && t1 <= game.player[0].len() as u64 && t2 <= game.player[1].len() as u64 {
-        let mut ng = game.clone();
-        ng.player[0] = ng.player[0].iter().take(t1 as usize).cloned().collect::<VecDeque<_>>();
-        ng.player[1] = ng.player[1].iter().take(t2 as usize).cloned().collect::<VecDeque<_>>();
-        let rg = play_game(ng, recurse, level + 1);
-        winner = rg.winner.unwrap();
-    } else {
-        if t2 > t1 {
-            winner = 1;
+        for (i, card) in active.iter().zip(cards.iter()) {
+            log!(self, "Player {} plays: {}", i + 1, card);
+        }
+
+        let left = active.iter()
+            .map(|i| game.player[*i].len())
+            .collect::<Vec<_>>();
+        let recurse = self.rules.may_recurse(depth) && active.len() > 1 && self.rules.trigger.fires(&cards, &left);
+
+        let winner = if recurse {
+            log!(self, "Playing a sub-game to determine the winner...\n");
+
+            let player = active.iter()
+                .zip(cards.iter())
+                .map(|(i, c)| game.player[*i].iter().take(*c as usize).cloned().collect())
+                .collect();
+            let mut sub_game = Game { player, winner: None };
+            self.play_game(&mut sub_game, depth + 1);
+
+            log!(self, "...anyway, back to game {}.", id);
+            sub_game.winner
+        } else {
+            highest_card(&cards, self.rules.tie_break)
+        };
+
+        let winner = match winner {
+            Some(w) => w,
+            None => {
+                log!(self, "Nobody wins the round!\n");
+                return;
+            },
+        };
+
+        if self.rules.is_recursive() {
+            log!(self, "Player {} wins round {} of game {}!\n", active[winner] + 1, round, id);
+        } else {
+            log!(self, "Player {} wins the round!\n", active[winner] + 1);
+        }
+
+        // the winner's card goes first, followed by the other cards in the
+        // order of the players
+        let deck = &mut game.player[active[winner]];
+        deck.push_back(cards[winner]);
+        for (i, card) in cards.iter().enumerate() {
+            if i != winner {
+                deck.push_back(*card);
+            }
         }
     }
+}
+
+// the index of the card that wins the round, none if the cards are discarded
+fn highest_card(cards: &[u64], tie_break: TieBreak) -> Option<usize> {
+    let max = cards.iter().max()?;
+    let highest = cards.iter()
+        .enumerate()
+        .filter(|(_, c)| *c == max)
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
 
-    if winner == 0 {
-        game.player[0].push_back(t1);
-        game.player[0].push_back(t2);
-    } else {
-        game.player[1].push_back(t2);
-        game.player[1].push_back(t1);
+    if highest.len() == 1 {
+        return Some(highest[0]);
     }
 
-    return game;
+    match tie_break {
+        TieBreak::FirstPlayer => highest.first().cloned(),
+        TieBreak::LastPlayer => highest.last().cloned(),
+        TieBreak::Discard => None,
+    }
 }
 
-fn play_game(mut game: Game, recurse: bool, level: usize) -> Game {
-    let mut pg = vec![];
-    loop {
-        let next_game = turn(game.clone(), &mut pg, recurse, level);
-        pg.push(game);
-        game = next_game;
-        if game.winner.is_some() {
-            return game;
-        }
-    }
+fn players_with_cards(game: &Game) -> usize {
+    game.player.iter().filter(|d| !d.is_empty()).count()
 }
 
-pub fn problem1() -> Result<(), ParseError> {
-    let decks = parse_input();
-    let mut game = Game { player: decks, winner: None };
+// the game ends as soon as at most one player has cards left. if all cards
+// have been discarded there is no winner.
+fn winner_by_cards(game: &Game) -> Option<usize> {
+    let mut players_with_cards = game.player.iter()
+        .enumerate()
+        .filter(|(_, d)| !d.is_empty());
 
-    loop {
-        game = turn(game, &mut vec![], false, 1);
-        if game.winner.is_some() {
-            break;
-        }
+    match (players_with_cards.next(), players_with_cards.next()) {
+        (Some((i, _)), None) => Some(i),
+        _ => None,
     }
+}
+
+fn winning_score(game: &Game, rules: &Rules) -> u64 {
+    game.winner
+        .map(|w| (rules.score)(&game.player[w]))
+        .unwrap_or(0)
+}
 
-    let winner = game.winner.unwrap();
-    let score: u64 = game.player[winner].iter().rev().enumerate()
-        .map(|(i, v)| (i + 1, v))
-        .map(|(i, v)| (i as u64) * v)
-        .sum();
+pub fn problem1() -> Result<(), ParseError> {
+    let decks = parse_input(include_str!("./data/input.txt"))?;
+    let rules = Rules::regular();
+    let game = Combat::new(rules, false).play(decks);
 
-    println!("22/1: score of winner's deck: {}", score);
+    println!("22/1: score of winner's deck: {}", winning_score(&game, &rules));
 
     Ok(())
 }
 
 pub fn problem2() -> Result<(), ParseError> {
-    let decks = parse_input();
-    let mut game = Game { player: decks, winner: None };
+    let decks = parse_input(include_str!("./data/input.txt"))?;
+    let rules = Rules::recursive();
+    let game = Combat::new(rules, false).play(decks);
+
+    println!("22/2: score of winner's deck: {}", winning_score(&game, &rules));
+
+    Ok(())
+}
+
+// `--max-depth` implies `--recursive`, a depth of 0 would be regular Combat
+// and is rejected
+fn recursion(args: &Args) -> Result<Recursion, ParseError> {
+    match (args.flag("recursive"), args.parsed_option::<usize>("max-depth")?) {
+        (_, Some(0)) => Err(ParseError::new("--max-depth must be at least 1, leave out --recursive for regular Combat")),
+        (_, Some(depth)) => Ok(Recursion::Limited(depth)),
+        (true, None) => Ok(Recursion::Always),
+        (false, None) => Ok(Recursion::Never),
+    }
+}
 
-    game = play_game(game, true, 1);
+pub fn command(args: &Args) -> Result<(), ParseError> {
+    if args.positional(1) == Some("simulate") {
+        let simulation = Simulation {
+            players: args.parsed_option::<usize>("decks")?.unwrap_or(2),
+            cards: args.parsed_option::<u64>("cards")?.unwrap_or(10),
+            games: args.parsed_option::<usize>("games")?.unwrap_or(1000),
+            max_rounds: args.parsed_option::<usize>("max-rounds")?.unwrap_or(10_000),
+        };
+        let seed = args.parsed_option::<u64>("seed")?;
+
+        print!("{}", simulation.run(seed)?);
+        return Ok(());
+    }
 
-    let winner = game.winner.unwrap();
-    let score: u64 = game.player[winner].iter().rev().enumerate()
-        .map(|(i, v)| (i + 1, v))
-        .map(|(i, v)| (i as u64) * v)
-        .sum();
+    let decks = parse_input(&args.input(include_str!("./data/input.txt"))?)?;
 
-    println!("22/2: score of winner's deck: {}", score);
+    let rules = Rules {
+        recursion: recursion(args)?,
+        trigger: args.parsed_option::<Trigger>("trigger")?.unwrap_or(Trigger::AllPlayers),
+        tie_break: args.parsed_option::<TieBreak>("tie")?.unwrap_or(TieBreak::FirstPlayer),
+        score: rules::scoring(args.option("score").unwrap_or("standard"))?,
+        max_rounds: args.parsed_option::<usize>("max-rounds")?,
+    };
+
+    let mut combat = Combat::new(rules, args.flag("log"));
+    let game = combat.play(decks);
+
+    if let Some(log) = &combat.log {
+        println!("{}", log);
+    }
+
+    match game.winner {
+        Some(w) => println!("player {} wins with a score of {}", w + 1, winning_score(&game, &rules)),
+        None => println!("all cards were discarded, nobody wins"),
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn example_1_1() {
+        let decks = parse_input(include_str!("./data/example.txt")).unwrap();
+        let mut combat = Combat::new(Rules::regular(), true);
+        let game = combat.play(decks);
+
+        assert_eq!(306, winning_score(&game, &Rules::regular()));
+
+        let log = combat.log.unwrap();
+        assert!(log.starts_with("-- Round 1 --
+Player 1's deck: 9, 2, 6, 3, 1
+Player 2's deck: 5, 8, 4, 7, 10
+Player 1 plays: 9
+Player 2 plays: 5
+Player 1 wins the round!
+
+-- Round 2 --
+"));
+        assert!(log.ends_with("-- Round 29 --
+Player 1's deck: 1
+Player 2's deck: 7, 3, 2, 10, 6, 8, 5, 9, 4
+Player 1 plays: 1
+Player 2 plays: 7
+Player 2 wins the round!
+
+== Post-game results ==
+Player 1's deck: 
+Player 2's deck: 3, 2, 10, 6, 8, 5, 9, 4, 7, 1
+"));
+    }
+
+    #[test]
+    pub fn example_2_1() {
+        let decks = parse_input(include_str!("./data/example.txt")).unwrap();
+        let mut combat = Combat::new(Rules::recursive(), true);
+        let game = combat.play(decks);
+
+        assert_eq!(291, winning_score(&game, &Rules::recursive()));
+        assert_eq!(5, combat.games);
+
+        let log = combat.log.unwrap();
+        assert!(log.contains("-- Round 9 (Game 1) --
+Player 1's deck: 4, 9, 8, 5, 2
+Player 2's deck: 3, 10, 1, 7, 6
+Player 1 plays: 4
+Player 2 plays: 3
+Playing a sub-game to determine the winner...
+
+=== Game 2 ===
+
+-- Round 1 (Game 2) --
+Player 1's deck: 9, 8, 5, 2
+Player 2's deck: 10, 1, 7
+"));
+        assert!(log.contains("The winner of game 2 is player 2!
+
+...anyway, back to game 1.
+Player 2 wins round 9 of game 1!
+"));
+        assert!(log.ends_with("The winner of game 1 is player 2!
+
+
+== Post-game results ==
+Player 1's deck: 
+Player 2's deck: 7, 5, 6, 2, 4, 1, 10, 8, 9, 3
+"));
+    }
+
+    #[test]
+    pub fn repeated_states_end_the_game() {
+        let decks = parse_input(include_str!("./data/recursion.txt")).unwrap();
+        let game = Combat::new(Rules::recursive(), false).play(decks);
+
+        assert_eq!(Some(0), game.winner);
+    }
+
+    #[test]
+    pub fn more_than_two_players() {
+        let decks = parse_input("Player 1:\n5\n1\n\nPlayer 2:\n3\n6\n\nPlayer 3:\n4\n2\n").unwrap();
+        let mut combat = Combat::new(Rules::regular(), true);
+        let game = combat.play(decks);
+
+        // player 3 drops out after the second round, player 2 wins after nine
+        assert_eq!(Some(1), game.winner);
+        assert_eq!(vec![5, 1, 6, 4, 3, 2], game.player[1].iter().cloned().collect::<Vec<_>>());
+
+        let log = combat.log.unwrap();
+        assert!(log.contains("-- Round 1 --
+Player 1's deck: 5, 1
+Player 2's deck: 3, 6
+Player 3's deck: 4, 2
+Player 1 plays: 5
+Player 2 plays: 3
+Player 3 plays: 4
+Player 1 wins the round!
+"));
+        assert!(log.contains("-- Round 3 --
+Player 1's deck: 5, 3, 4
+Player 2's deck: 6, 1, 2
+Player 3's deck: 
+Player 1 plays: 5
+Player 2 plays: 6
+Player 2 wins the round!
+"));
+    }
+
+    #[test]
+    pub fn rule_variants() {
+        let decks = || parse_input("Player 1:\n5\n1\n\nPlayer 2:\n5\n2\n").unwrap();
+
+        let rules = Rules { tie_break: TieBreak::LastPlayer, ..Rules::regular() };
+        let game = Combat::new(rules, false).play(decks());
+        assert_eq!(Some(1), game.winner);
+
+        let rules = Rules { tie_break: TieBreak::Discard, score: rules::sum_score, ..Rules::regular() };
+        let game = Combat::new(rules, false).play(decks());
+        assert_eq!(Some(1), game.winner);
+        assert_eq!(3, winning_score(&game, &rules));
+
+        let decks = parse_input(include_str!("./data/example.txt")).unwrap();
+        let rules = Rules { recursion: Recursion::Limited(1), score: rules::count_score, ..Rules::regular() };
+        let mut combat = Combat::new(rules, false);
+        let game = combat.play(decks);
+        assert_eq!(4, combat.games);
+        assert_eq!(10, winning_score(&game, &rules));
+
+        let decks = parse_input(include_str!("./data/example.txt")).unwrap();
+        let rules = Rules { max_rounds: Some(10), ..Rules::regular() };
+        let mut combat = Combat::new(rules, false);
+        let game = combat.play(decks);
+        assert_eq!(None, game.winner);
+        assert_eq!(10, game.player.iter().map(|d| d.len()).sum::<usize>());
+    }
+
+    #[test]
+    pub fn sub_game_triggers() {
+        assert!(Trigger::AllPlayers.fires(&[2, 3], &[2, 3]));
+        assert!(!Trigger::AllPlayers.fires(&[2, 4], &[2, 3]));
+        assert!(Trigger::AnyPlayer.fires(&[2, 4], &[2, 3]));
+        assert!(!Trigger::AnyPlayer.fires(&[3, 4], &[2, 3]));
+        assert!(Trigger::CardsUpTo(3).fires(&[2, 3], &[2, 3]));
+        assert!(!Trigger::CardsUpTo(2).fires(&[2, 3], &[2, 3]));
+
+        assert_eq!(Trigger::CardsUpTo(5), "up-to:5".parse::<Trigger>().unwrap());
+        assert_eq!(Trigger::AnyPlayer, "any".parse::<Trigger>().unwrap());
+        assert!("up-to:x".parse::<Trigger>().is_err());
+        assert!("most".parse::<Trigger>().is_err());
+
+        let games = |trigger| {
+            let decks = parse_input(include_str!("./data/example.txt")).unwrap();
+            let mut combat = Combat::new(Rules { trigger, ..Rules::recursive() }, false);
+            let game = combat.play(decks);
+            (game.winner, combat.games)
+        };
+
+        assert_eq!((Some(1), 5), games(Trigger::AllPlayers));
+        assert_eq!((Some(1), 5), games(Trigger::CardsUpTo(10)));
+        assert_eq!((Some(1), 1), games(Trigger::CardsUpTo(1)));
+        assert_eq!((Some(0), 11), games(Trigger::AnyPlayer));
+    }
+
+    #[test]
+    pub fn depth_zero_is_regular_combat() {
+        let play = |recursion| {
+            let decks = parse_input(include_str!("./data/example.txt")).unwrap();
+            let mut combat = Combat::new(Rules { recursion, ..Rules::regular() }, true);
+            let game = combat.play(decks);
+            (game.winner, combat.rounds, combat.games, combat.log)
+        };
+
+        assert_eq!(play(Recursion::Never), play(Recursion::Limited(0)));
+    }
+
+    #[test]
+    pub fn recursion_from_command_line() {
+        let recursion = |s: &str| {
+            let v = s.split(' ').map(|v| v.to_string()).collect::<Vec<_>>();
+            recursion(&Args::parse(&v))
+        };
+
+        assert_eq!(Recursion::Never, recursion("combat").unwrap());
+        assert_eq!(Recursion::Always, recursion("combat --recursive").unwrap());
+        assert_eq!(Recursion::Limited(2), recursion("combat --max-depth 2").unwrap());
+        assert_eq!(Recursion::Limited(2), recursion("combat --recursive --max-depth 2").unwrap());
+        assert!(recursion("combat --max-depth 0").is_err());
+        assert!(recursion("combat --recursive --max-depth 0").is_err());
+    }
+
+    #[test]
+    pub fn rounds_are_capped() {
+        let play = |max_rounds| {
+            let decks = parse_input(include_str!("./data/example.txt")).unwrap();
+            let mut combat = Combat::new(Rules { max_rounds, ..Rules::regular() }, false);
+            let game = combat.play(decks);
+            (game.winner, combat.rounds)
+        };
+
+        assert_eq!((Some(1), 29), play(None));
+        assert_eq!((Some(1), 29), play(Some(29)));
+        assert_eq!((None, 28), play(Some(28)));
+        assert_eq!((None, 0), play(Some(0)));
+    }
+
+    #[test]
+    pub fn all_cards_discarded() {
+        let rules = Rules { tie_break: TieBreak::Discard, ..Rules::regular() };
+        let game = Combat::new(rules, false).play(parse_input("Player 1:\n5\n\nPlayer 2:\n5\n").unwrap());
+        assert_eq!(None, game.winner);
+        assert!(game.player.iter().all(|d| d.is_empty()));
+
+        let rules = Rules { tie_break: TieBreak::Discard, ..Rules::recursive() };
+        let mut combat = Combat::new(rules, false);
+        let game = combat.play(parse_input("Player 1:\n1\n5\n\nPlayer 2:\n1\n5\n").unwrap());
+        assert_eq!(None, game.winner);
+        assert_eq!(2, combat.games);
+        assert_eq!(0, winning_score(&game, &rules));
+    }
+
+    #[test]
+    pub fn games_are_measured() {
+        let decks = parse_input(include_str!("./data/example.txt")).unwrap();
+        let mut combat = Combat::new(Rules::recursive(), false);
+        combat.play(decks);
+
+        assert_eq!(17, combat.rounds);
+        assert_eq!(vec![1, 3, 1], combat.depths);
+        assert_eq!(0, combat.repetitions);
+
+        let decks = parse_input(include_str!("./data/recursion.txt")).unwrap();
+        let mut combat = Combat::new(Rules::recursive(), false);
+        combat.play(decks);
+        assert_eq!(1, combat.repetitions);
+        assert_eq!(6, combat.rounds);
+    }
+
+    #[test]
+    pub fn simulations_are_reproducible() {
+        let simulation = Simulation { players: 3, cards: 12, games: 50, max_rounds: 1000 };
+        let report = simulation.run(Some(7)).unwrap();
+
+        assert_eq!(report, simulation.run(Some(7)).unwrap());
+        assert!(report.contains("regular combat, 50 games"));
+        assert!(report.contains("recursive combat, 50 games"));
+        assert!(report.contains("player 3 wins:"));
+        assert!(Simulation { players: 3, cards: 2, games: 1, max_rounds: 10 }.run(None).is_err());
+    }
+}
@@ -0,0 +1,142 @@
+use std::str::FromStr;
+use super::super::utils::ParseError;
+use super::Deck;
+
+/// How deep rounds may be decided by a sub-game instead of the highest card.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Recursion {
+    Never,
+    // whenever the trigger allows it
+    Always,
+    // like `Always` but only up to the given depth of nested sub-games
+    Limited(usize),
+}
+
+/// Which rounds start a sub-game if the recursion allows it. Every player
+/// takes as many of their next cards into the sub-game as the value of the
+/// card they just played, or all of them if they have fewer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trigger {
+    // every player has at least as many cards left as the value of the card
+    // they just played
+    AllPlayers,
+    // at least one player has
+    AnyPlayer,
+    // like `AllPlayers` but only if no card played is higher than the given
+    // value
+    CardsUpTo(u64),
+}
+
+impl Trigger {
+    /// Whether a round in which the given cards were played is decided by a
+    /// sub-game, `left` holds the number of cards each player has left.
+    pub fn fires(&self, cards: &[u64], left: &[usize]) -> bool {
+        let mut enough = cards.iter().zip(left.iter()).map(|(c, l)| *c <= *l as u64);
+
+        match self {
+            Trigger::AllPlayers => enough.all(|e| e),
+            Trigger::AnyPlayer => enough.any(|e| e),
+            Trigger::CardsUpTo(max) => enough.all(|e| e) && cards.iter().all(|c| c <= max),
+        }
+    }
+}
+
+impl FromStr for Trigger {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Trigger, ParseError> {
+        match s.split_once(':') {
+            None if s == "all" => Ok(Trigger::AllPlayers),
+            None if s == "any" => Ok(Trigger::AnyPlayer),
+            Some(("up-to", max)) => Ok(Trigger::CardsUpTo(max.parse()?)),
+            _ => Err(ParseError::new(&format!("Unknown trigger: {}", s))),
+        }
+    }
+}
+
+/// Who wins a round in which several players played the same highest card.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TieBreak {
+    FirstPlayer,
+    LastPlayer,
+    // nobody wins, all cards played in the round leave the game
+    Discard,
+}
+
+impl FromStr for TieBreak {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<TieBreak, ParseError> {
+        match s {
+            "first" => Ok(TieBreak::FirstPlayer),
+            "last" => Ok(TieBreak::LastPlayer),
+            "discard" => Ok(TieBreak::Discard),
+            _ => Err(ParseError::new(&format!("Unknown tie break: {}", s))),
+        }
+    }
+}
+
+pub type Scoring = fn(&Deck) -> u64;
+
+/// Each card multiplied by its position counted from the bottom of the deck.
+pub fn standard_score(deck: &Deck) -> u64 {
+    deck.iter().rev().enumerate()
+        .map(|(i, v)| (i as u64 + 1) * v)
+        .sum()
+}
+
+pub fn sum_score(deck: &Deck) -> u64 {
+    deck.iter().sum()
+}
+
+pub fn count_score(deck: &Deck) -> u64 {
+    deck.len() as u64
+}
+
+pub fn scoring(s: &str) -> Result<Scoring, ParseError> {
+    match s {
+        "standard" => Ok(standard_score),
+        "sum" => Ok(sum_score),
+        "count" => Ok(count_score),
+        _ => Err(ParseError::new(&format!("Unknown scoring: {}", s))),
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Rules {
+    pub recursion: Recursion,
+    pub trigger: Trigger,
+    pub tie_break: TieBreak,
+    pub score: Scoring,
+    // games that take longer end without a winner, regular Combat does not
+    // stop repeating states and may otherwise never end
+    pub max_rounds: Option<usize>,
+}
+
+impl Rules {
+    /// The rules of the first part of the puzzle.
+    pub fn regular() -> Rules {
+        Rules { recursion: Recursion::Never, trigger: Trigger::AllPlayers, tie_break: TieBreak::FirstPlayer, score: standard_score, max_rounds: None }
+    }
+
+    /// The rules of the second part of the puzzle.
+    pub fn recursive() -> Rules {
+        Rules { recursion: Recursion::Always, ..Rules::regular() }
+    }
+
+    /// Whether the outermost game may start sub-games. `Limited(0)` plays
+    /// exactly like regular Combat.
+    pub fn is_recursive(&self) -> bool {
+        self.may_recurse(0)
+    }
+
+    /// Whether sub-games are allowed in a game at the given depth, the
+    /// outermost game has depth 0.
+    pub fn may_recurse(&self, depth: usize) -> bool {
+        match self.recursion {
+            Recursion::Never => false,
+            Recursion::Always => true,
+            Recursion::Limited(max) => depth < max,
+        }
+    }
+}
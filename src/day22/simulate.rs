@@ -0,0 +1,132 @@
+use std::fmt::Write;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use super::super::utils::ParseError;
+use super::{Combat, Deck};
+use super::rules::Rules;
+
+/// Plays many games of Combat with randomly dealt decks.
+pub struct Simulation {
+    pub players: usize,
+    pub cards: u64,
+    pub games: usize,
+    pub max_rounds: usize,
+}
+
+// results of all games played with one set of rules
+struct Results {
+    wins: Vec<usize>,
+    unfinished: usize,
+    rounds: usize,
+    depths: Vec<usize>,
+    games_with_repetition: usize,
+    repetitions: usize,
+}
+
+impl Results {
+    fn new(players: usize) -> Results {
+        Results { wins: vec![0; players], unfinished: 0, rounds: 0, depths: vec![], games_with_repetition: 0, repetitions: 0 }
+    }
+
+    fn add(&mut self, combat: &Combat, winner: Option<usize>) {
+        match winner {
+            Some(w) => self.wins[w] += 1,
+            None => self.unfinished += 1,
+        }
+
+        self.rounds += combat.rounds;
+
+        if self.depths.len() < combat.depths.len() {
+            self.depths.resize(combat.depths.len(), 0);
+        }
+        for (d, n) in combat.depths.iter().enumerate() {
+            self.depths[d] += n;
+        }
+
+        if combat.repetitions > 0 {
+            self.games_with_repetition += 1;
+        }
+        self.repetitions += combat.repetitions;
+    }
+
+    fn write(&self, out: &mut String, title: &str, games: usize) {
+        let percent = |n: usize| 100.0 * n as f64 / games as f64;
+
+        writeln!(out, "{}, {} games:", title, games).unwrap();
+        for (i, w) in self.wins.iter().enumerate() {
+            writeln!(out, "  player {} wins: {:.1}%", i + 1, percent(*w)).unwrap();
+        }
+        writeln!(out, "  no winner: {:.1}%", percent(self.unfinished)).unwrap();
+        writeln!(out, "  average game length: {:.1} rounds", self.rounds as f64 / games as f64).unwrap();
+
+        if self.depths.len() > 1 {
+            let depths = self.depths.iter()
+                .enumerate()
+                .map(|(d, n)| format!("{}: {}", d, n))
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(out, "  games by depth: {}", depths).unwrap();
+        }
+
+        writeln!(out, "  infinite-loop rule triggered in {:.1}% of games ({} times in total)",
+            percent(self.games_with_repetition), self.repetitions).unwrap();
+    }
+}
+
+impl Simulation {
+    // shuffles the cards 1 to `cards` and deals them to the players in turn
+    fn deal<R: Rng>(&self, rng: &mut R) -> Vec<Deck> {
+        let mut cards = (1..=self.cards).collect::<Vec<_>>();
+        cards.shuffle(rng);
+
+        let mut decks = vec![Deck::new(); self.players];
+        for (i, c) in cards.into_iter().enumerate() {
+            decks[i % self.players].push_back(c);
+        }
+
+        decks
+    }
+
+    /// Plays every dealt game once with regular and once with recursive
+    /// rules. Without a seed the decks are different for every run.
+    pub fn run(&self, seed: Option<u64>) -> Result<String, ParseError> {
+        if self.players < 2 || self.cards < self.players as u64 {
+            return Err(ParseError::new("Every player needs at least one card"));
+        }
+
+        if self.games == 0 {
+            return Err(ParseError::new("Nothing to simulate"));
+        }
+
+        let mut rng = match seed {
+            Some(s) => StdRng::seed_from_u64(s),
+            None => StdRng::from_entropy(),
+        };
+
+        let variants = [
+            ("regular combat", Rules { max_rounds: Some(self.max_rounds), ..Rules::regular() }),
+            ("recursive combat", Rules { max_rounds: Some(self.max_rounds), ..Rules::recursive() }),
+        ];
+        let mut results = variants.iter()
+            .map(|_| Results::new(self.players))
+            .collect::<Vec<_>>();
+
+        for _ in 0..self.games {
+            let decks = self.deal(&mut rng);
+
+            for ((_, rules), r) in variants.iter().zip(results.iter_mut()) {
+                let mut combat = Combat::new(*rules, false);
+                let game = combat.play(decks.clone());
+                r.add(&combat, game.winner);
+            }
+        }
+
+        let mut out = String::new();
+        for ((title, _), r) in variants.iter().zip(results.iter()) {
+            r.write(&mut out, title, self.games);
+        }
+
+        Ok(out)
+    }
+}
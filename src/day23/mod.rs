@@ -1,103 +1,250 @@
-use std::collections::HashMap;
-use std::collections::VecDeque;
 use itertools::join;
-use indicatif::ProgressBar;
 use super::utils::ParseError;
+use super::cli::Args;
 
-fn get_input() -> Vec<usize> {
-    vec![4, 6, 3, 5, 2, 8, 1, 7, 9]
-}
+/// Labels are either given as digits like `389125467` or, for labels larger
+/// than 9, separated by commas.
+fn parse_input(input: &str) -> Result<Vec<u32>, ParseError> {
+    let input = input.trim();
 
-fn get_example() -> Vec<usize> {
-    vec![3, 8, 9, 1, 2, 5, 4, 6, 7]
+    if input.contains(',') {
+        input.split(',')
+            .map(|v| v.trim().parse::<u32>().map_err(ParseError::from))
+            .collect()
+    } else {
+        input.chars()
+            .map(|c| c.to_digit(10).ok_or_else(|| ParseError::new(&format!("Not a cup label: '{}'", c))))
+            .collect()
+    }
 }
 
-fn input_to_map(input: &Vec<usize>) -> HashMap<usize, usize> {
-    let mut map = HashMap::new();
-    for i in 0..input.len()-1 {
-        map.entry(input[i]).or_insert(input[i+1]);
-    }
-    map.entry(*input.last().unwrap()).or_insert(*input.first().unwrap());
-    map
+#[derive(Debug, Clone, Copy)]
+struct Rules {
+    cups: usize,
+    pick_up: usize,
+    moves: usize,
 }
 
+#[derive(Clone)]
 struct State {
-    current: usize,
-    max: usize,
-    map: HashMap<usize, usize>,
+    current: u32,
+    // next[label] is the label of the cup clockwise of the cup with the given
+    // label, next[0] is unused.
+    next: Vec<u32>,
+    picked: Vec<u32>,
 }
 
 impl State {
-    fn print(&self) {
+    /// Places the cups with the given labels in a circle, followed by cups
+    /// with increasing labels until there are `cups` cups in total.
+    fn new(labels: &[u32], cups: usize) -> Result<State, ParseError> {
+        if labels.is_empty() || cups < labels.len() {
+            return Err(ParseError::new("Not enough cups"));
+        }
+
+        if cups > u32::MAX as usize {
+            return Err(ParseError::new("Too many cups"));
+        }
+
+        let mut seen = vec![false; labels.len() + 1];
+        for &l in labels {
+            let l = l as usize;
+            if l == 0 || l > labels.len() || seen[l] {
+                return Err(ParseError::new(&format!("Labels must be the numbers 1 to {}", labels.len())));
+            }
+            seen[l] = true;
+        }
+
+        let order = labels.iter()
+            .cloned()
+            .chain(labels.len() as u32 + 1..=cups as u32)
+            .collect::<Vec<_>>();
+
+        let mut next = vec![0; cups + 1];
+        for w in order.windows(2) {
+            next[w[0] as usize] = w[1];
+        }
+        next[order[cups - 1] as usize] = order[0];
+
+        Ok(State { current: order[0], next, picked: vec![] })
+    }
+
+    fn cups(&self) -> u32 {
+        (self.next.len() - 1) as u32
+    }
+
+    fn successor(&self, label: u32) -> u32 {
+        self.next[label as usize]
+    }
+
+    fn turn(&mut self, pick_up: usize) {
+        self.picked.clear();
+        let mut last = self.current;
+        for _ in 0..pick_up {
+            last = self.successor(last);
+            self.picked.push(last);
+        }
+
+        let cups = self.cups();
+        let mut destination = self.current;
+        loop {
+            destination = if destination == 1 { cups } else { destination - 1 };
+            if !self.picked.contains(&destination) {
+                break;
+            }
+        }
+
+        if let Some(&first) = self.picked.first() {
+            let after_destination = self.successor(destination);
+            let after_last = self.successor(last);
+            self.next[destination as usize] = first;
+            self.next[last as usize] = after_destination;
+            self.next[self.current as usize] = after_last;
+        }
+
+        self.current = self.successor(self.current);
+    }
+
+    // picked is only scratch space and not part of the state
+    fn same_as(&self, other: &State) -> bool {
+        self.current == other.current && self.next == other.next
+    }
+
+    /// All cups in clockwise order starting with cup 1.
+    fn arrangement(&self) -> Arrangement<'_> {
+        Arrangement { state: self, next: Some(1) }
+    }
+
+    /// The labels of the cups clockwise of cup 1.
+    fn after_1(&self, n: usize) -> Vec<u32> {
         let mut result = vec![];
+        let mut previous = 1;
 
-        let mut current = self.current;
-        while result.len() != self.map.len() {
-            result.push(current);
-            current = self.map[&current];
+        for _ in 0..n {
+            previous = self.successor(previous);
+            result.push(previous);
         }
-        println!("{:?}", result);
+
+        result
     }
 }
 
-fn pick_three(state: &mut State) -> usize {
-    state.map[&state.current]
+struct Arrangement<'a> {
+    state: &'a State,
+    next: Option<u32>,
 }
 
-fn get_destination(max: usize, destination: usize) -> usize {
-    if destination == 1 {
-        max
-    } else {
-        destination - 1
+impl<'a> Iterator for Arrangement<'a> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        let label = self.next?;
+        let successor = self.state.successor(label);
+        self.next = if successor == 1 { None } else { Some(successor) };
+
+        Some(label)
     }
 }
 
-fn turn(state: &mut State) {
-    let three = pick_three(state);
+// comparing states after every move doubles the cost of a move, which only
+// pays off for a small number of cups
+const CYCLE_DETECTION_LIMIT: usize = 16;
 
-    let first = three;
-    let second = state.map[&first];
-    let third = state.map[&second];
+/// The states after move `start` and `start + period` are the same, so the
+/// game repeats itself from then on. A period of 1 is a fixed point.
+#[derive(Debug, PartialEq)]
+struct Cycle {
+    start: usize,
+    period: usize,
+}
 
-    let mut destination = get_destination(state.max, state.current);
-    while first == destination || second == destination || third == destination {
-        destination = get_destination(state.max, destination);
+fn check_rules(rules: &Rules) -> Result<(), ParseError> {
+    if rules.pick_up + 2 > rules.cups {
+        return Err(ParseError::new("Not enough cups to pick up"));
     }
 
-    let after_destination = state.map[&destination];
-    let after_third = state.map[&third];
-    state.map.entry(destination).and_modify(|v| *v = first);
-    state.map.entry(third).and_modify(|v| *v = after_destination);
-    state.map.entry(state.current).and_modify(|v| *v = after_third);
+    Ok(())
+}
 
-    state.current = state.map[&state.current];
+fn play(labels: &[u32], rules: &Rules) -> Result<State, ParseError> {
+    play_with_cycle_detection(labels, rules).map(|(state, _)| state)
 }
 
-fn rotate_to_1(cups: &mut VecDeque<u64>) {
-    while cups[0] != 1 {
-        cups.rotate_left(1);
+// the first move after which the game repeats itself, found by starting a
+// second game `period` moves ahead and playing both until they meet
+fn cycle_start(labels: &[u32], rules: &Rules, period: usize) -> Result<usize, ParseError> {
+    let mut tortoise = State::new(labels, rules.cups)?;
+    let mut hare = tortoise.clone();
+    for _ in 0..period {
+        hare.turn(rules.pick_up);
     }
+
+    let mut start = 0;
+    while !tortoise.same_as(&hare) {
+        tortoise.turn(rules.pick_up);
+        hare.turn(rules.pick_up);
+        start += 1;
+    }
+
+    Ok(start)
 }
 
-fn checksum(state: &State) -> String {
-    let mut result = vec![];
-    let mut previous = 1;
+/// Plays the game like `play` but, for small numbers of cups, watches for
+/// states that occurred before and skips the remaining full cycles.
+///
+/// Cycles are found with Brent's algorithm, which only keeps one earlier
+/// state around: it is replaced by the current state whenever the number of
+/// moves since it was taken reaches the next power of two.
+fn play_with_cycle_detection(labels: &[u32], rules: &Rules) -> Result<(State, Option<Cycle>), ParseError> {
+    check_rules(rules)?;
+
+    let mut state = State::new(labels, rules.cups)?;
+    let mut cycle = None;
+    let mut moves = 0;
 
-    for _ in 1..9 {
-        previous = state.map[&previous];
-        result.push(previous);
+    if rules.cups <= CYCLE_DETECTION_LIMIT {
+        let mut earlier = state.clone();
+        let mut power = 1;
+        let mut period = 0;
+
+        while moves < rules.moves {
+            state.turn(rules.pick_up);
+            moves += 1;
+            period += 1;
+
+            if state.same_as(&earlier) {
+                cycle = Some(Cycle { start: cycle_start(labels, rules, period)?, period });
+                moves += (rules.moves - moves) / period * period;
+                break;
+            }
+
+            if period == power {
+                earlier = state.clone();
+                power *= 2;
+                period = 0;
+            }
+        }
+    }
+
+    while moves < rules.moves {
+        state.turn(rules.pick_up);
+        moves += 1;
     }
-    join(result, "")
+
+    Ok((state, cycle))
 }
 
-pub fn problem1() -> Result<(), ParseError> {
-    let input = get_input();
-    let cups = input_to_map(&input);
-    let mut state = State { current: input[0], max: 9, map: cups };
+fn checksum(state: &State) -> String {
+    join(state.after_1(state.cups() as usize - 1), "")
+}
 
-    for _ in 0..100 {
-        turn(&mut state);
-    }
+fn star_product(state: &State) -> u64 {
+    state.after_1(2).iter().map(|v| *v as u64).product()
+}
+
+pub fn problem1() -> Result<(), ParseError> {
+    let input = parse_input(include_str!("./data/input.txt"))?;
+    let state = play(&input, &Rules { cups: input.len(), pick_up: 3, moves: 100 })?;
 
     println!("23/1: order of cups starting with 1 except 1: {}", checksum(&state));
 
@@ -105,29 +252,99 @@ pub fn problem1() -> Result<(), ParseError> {
 }
 
 pub fn problem2() -> Result<(), ParseError> {
-    let mut cups = (1..=1_000_000).collect::<Vec<usize>>();
-    let first_10 = get_input();
+    let input = parse_input(include_str!("./data/input.txt"))?;
+    let state = play(&input, &Rules { cups: 1_000_000, pick_up: 3, moves: 10_000_000 })?;
 
-    for (i, v) in first_10.into_iter().enumerate() {
-        cups[i] = v;
-    }
+    println!("23/2: product of the two cups the two stars are under: {}", star_product(&state));
+
+    Ok(())
+}
 
-    let map = input_to_map(&cups);
-    let mut state = State { current: cups[0], max: 1_000_000, map };
+pub fn command(args: &Args) -> Result<(), ParseError> {
+    let input = parse_input(&args.input(include_str!("./data/input.txt"))?)?;
+    let rules = Rules {
+        cups: args.parsed_option::<usize>("cups")?.unwrap_or(input.len()),
+        pick_up: args.parsed_option::<usize>("pick-up")?.unwrap_or(3),
+        moves: args.parsed_option::<usize>("moves")?.unwrap_or(100),
+    };
 
-    let iterations = 10_000_000;
-    let pb = ProgressBar::new(iterations);
-    for t in 0..iterations {
-        if t % 100_000 == 0 {
-            pb.inc(100_000);
-        }
-        turn(&mut state);
+    let (state, cycle) = play_with_cycle_detection(&input, &rules)?;
+
+    if let Some(c) = cycle {
+        println!("state after move {} repeats every {} moves", c.start, c.period);
     }
-    pb.finish_and_clear();
 
-    let star1 = state.map[&1];
-    let star2 = state.map[&star1];
-    println!("23/2: product of the two cups the two stars are under: {}", star1 * star2);
+    println!("cups starting with cup 1: {}", join(state.arrangement().take(11), ","));
+    println!("product of the two cups after cup 1: {}", star_product(&state));
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn example_1_1() {
+        let input = parse_input(include_str!("./data/example.txt")).unwrap();
+
+        let state = play(&input, &Rules { cups: 9, pick_up: 3, moves: 10 }).unwrap();
+        assert_eq!("92658374", checksum(&state));
+
+        let state = play(&input, &Rules { cups: 9, pick_up: 3, moves: 100 }).unwrap();
+        assert_eq!("67384529", checksum(&state));
+    }
+
+    #[test]
+    pub fn example_2_1() {
+        let input = parse_input(include_str!("./data/example.txt")).unwrap();
+        let state = play(&input, &Rules { cups: 1_000_000, pick_up: 3, moves: 10_000_000 }).unwrap();
+
+        assert_eq!(149245887792, star_product(&state));
+    }
+
+    #[test]
+    pub fn cycles_are_skipped() {
+        let input = parse_input(include_str!("./data/example.txt")).unwrap();
+        let rules = Rules { cups: 9, pick_up: 3, moves: 1_000_000_000 };
+        let (state, cycle) = play_with_cycle_detection(&input, &rules).unwrap();
+        let cycle = cycle.unwrap();
+
+        let moves = cycle.start + (rules.moves - cycle.start) % cycle.period;
+        let expected = play_with_cycle_detection(&input, &Rules { moves, ..rules }).unwrap().0;
+        assert_eq!(expected.arrangement().collect::<Vec<_>>(), state.arrangement().collect::<Vec<_>>());
+        assert_eq!(expected.current, state.current);
+
+        // with one cup picked up out of three the cups just swap places
+        let input = parse_input("123").unwrap();
+        let (_, cycle) = play_with_cycle_detection(&input, &Rules { cups: 3, pick_up: 1, moves: 10 }).unwrap();
+        assert_eq!(Some(Cycle { start: 0, period: 2 }), cycle);
+    }
+
+    #[test]
+    pub fn arrangement_starts_with_1() {
+        let input = parse_input(include_str!("./data/example.txt")).unwrap();
+
+        let state = play(&input, &Rules { cups: 9, pick_up: 3, moves: 0 }).unwrap();
+        assert_eq!(vec![1, 2, 5, 4, 6, 7, 3, 8, 9], state.arrangement().collect::<Vec<_>>());
+
+        let state = play(&input, &Rules { cups: 12, pick_up: 3, moves: 1 }).unwrap();
+        assert_eq!(vec![1, 5, 4, 6, 7, 10, 11, 12, 3, 2, 8, 9], state.arrangement().collect::<Vec<_>>());
+    }
+
+    #[test]
+    pub fn configurable_rules() {
+        let input = parse_input("3,1,2").unwrap();
+
+        // picking up one cup of three swaps the other two each move
+        let state = play(&input, &Rules { cups: 3, pick_up: 1, moves: 1 }).unwrap();
+        assert_eq!(vec![3, 2], state.after_1(2));
+
+        let state = play(&input, &Rules { cups: 3, pick_up: 0, moves: 5 }).unwrap();
+        assert_eq!(vec![2, 3], state.after_1(2));
+
+        assert!(play(&input, &Rules { cups: 3, pick_up: 2, moves: 1 }).is_err());
+        assert!(parse_input("1,3").and_then(|l| State::new(&l, 2)).is_err());
+        assert!(parse_input("12x").is_err());
+    }
+}
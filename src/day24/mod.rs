@@ -1,217 +1,217 @@
-use std::collections::HashMap;
-use std::ops::Add;
-use indicatif::ProgressBar;
+use std::collections::{HashMap, HashSet};
 use super::utils::ParseError;
+use super::cli::Args;
+use super::utils::hex::{Cube, Direction, parse_direction_lines};
 
-#[derive(Debug)]
-enum Direction {
-    NorthEast,
-    East,
-    SouthEast,
-    SouthWest,
-    West,
-    NorthWest,
-}
+mod svg;
+mod path;
 
-fn parse_line(s: &str) -> Vec<Direction> {
-    let mut i = s.chars().peekable();
-    let mut result = vec![];
-
-    while let Some(c) = i.next() {
-        let p = i.peek();
-
-        let d = match (c, p) {
-            ('s', Some('e')) => {
-                i.next();
-                Direction::SouthEast
-            },
-            ('s', Some('w')) => {
-                i.next();
-                Direction::SouthWest
-            },
-            ('w', _) => {
-                Direction::West
-            },
-            ('e', _) => {
-                Direction::East
-            },
-            ('n', Some('e')) => {
-                i.next();
-                Direction::NorthEast
-            },
-            ('n', Some('w')) => {
-                i.next();
-                Direction::NorthWest
-            },
-            (v1, v2) => panic!(format!("Encountered unexpected pair of input chars: '{}' and '{:?}'", v1, v2))
-        };
-
-        result.push(d);
-    }
+use svg::Renderer;
 
-    result
-}
+type Coords = Cube;
 
-fn parse_input() -> Vec<Vec<Direction>> {
-    let input = include_str!("./data/input.txt");
-    input
-        .lines()
-        .filter(|v| *v != "")
-        .map(|v| parse_line(v))
-        .collect::<Vec<_>>()
+fn parse_input(input: &str) -> Result<Vec<Vec<Direction>>, ParseError> {
+    Ok(parse_direction_lines(input)?)
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct Coords {
-    x: i64,
-    y: i64,
-    z: i64,
+fn get_tile(instructions: &[Direction]) -> Coords {
+    instructions.iter()
+        .map(|d| d.cube())
+        .fold(Coords::zeroes(), |a, c| a + c)
 }
 
-impl Coords {
-    pub fn new(x: i64, y: i64, z: i64) -> Self {
-        Self { x, y, z }
-    }
+// The floor only stores its black tiles, every other tile is white.
+type Floor = HashSet<Coords>;
 
-    pub fn zeroes() -> Self {
-        Self { x: 0, y: 0, z: 0 }
-    }
+fn flip_tiles(input: &[Vec<Direction>]) -> Floor {
+    let mut floor = Floor::new();
 
-    pub fn from_direction(d: &Direction) -> Self {
-        let (x, y, z) = match d {
-            Direction::NorthEast => (1, -1, 0),
-            Direction::East => (1, 0, -1),
-            Direction::SouthEast => (0, 1, -1),
-            Direction::SouthWest => (-1, 1, 0),
-            Direction::West => (-1, 0, 1),
-            Direction::NorthWest => (0, -1, 1),
-        };
-
-        Self { x, y, z }
+    for tile in input {
+        let coords = get_tile(tile);
+        if !floor.remove(&coords) {
+            floor.insert(coords);
+        }
     }
 
-    pub fn abs(&self) -> u64 {
-        (self.x.abs() + self.y.abs() + self.z.abs()) as u64
-    }
+    floor
 }
 
-impl Add for Coords {
-    type Output = Self;
+pub fn problem1() -> Result<(), ParseError> {
+    let input = parse_input(include_str!("./data/input.txt"))?;
 
-    fn add(self, other: Self) -> Self {
-        Self {
-            x: self.x + other.x,
-            y: self.y + other.y,
-            z: self.z + other.z,
-        }
-    }
-}
+    let result = flip_tiles(&input).len();
 
-fn get_tile(instructions: &Vec<Direction>) -> Coords {
-    let tile = instructions.iter()
-        .map(|d| Coords::from_direction(d))
-        .fold(Coords::zeroes(), |a, c| a + c);
+    println!("24/1: # of flipped tiles: {}", result);
 
-    tile
+    Ok(())
 }
 
-pub fn problem1() -> Result<(), ParseError> {
-    let input = parse_input();
+// only black tiles and their neighbors can be black on the next day, so we
+// count the black neighbors of exactly these tiles.
+fn tick(floor: &Floor) -> Floor {
+    let mut black_neighbors: HashMap<Coords, usize> = HashMap::new();
 
-    let mut floor: HashMap<Coords, bool> = HashMap::new();
-    for tile in input.iter() {
-        let coords = get_tile(tile);
-        floor.entry(coords)
-            .and_modify(|v| *v = !*v)
-            .or_insert(true);
+    for tile in floor {
+        for d in &Direction::ALL {
+            *black_neighbors.entry(*tile + d.cube()).or_insert(0) += 1;
+        }
     }
 
-    let result = floor.iter()
-        .filter(|(_, v)| **v)
-        .count();
+    black_neighbors.into_iter()
+        .filter(|(tile, count)| {
+            if floor.contains(tile) {
+                *count == 1 || *count == 2
+            } else {
+                *count == 2
+            }
+        })
+        .map(|(tile, _)| tile)
+        .collect()
+}
 
-    println!("24/1: # of flipped tiles: {}", result);
+fn simulate(floor: Floor, days: usize) -> Floor {
+    (0..days).fold(floor, |floor, _| tick(&floor))
+}
 
+pub fn problem2() -> Result<(), ParseError> {
+    let input = parse_input(include_str!("./data/input.txt"))?;
+
+    let floor = simulate(flip_tiles(&input), 100);
+    let result = floor.len();
+
+    println!("24/2: # of flipped tiles: {}", result);
     Ok(())
 }
 
-// Create a floor big enough for part 2
-fn initialize_floor() -> HashMap<Coords, bool> {
-    let mut floor: HashMap<Coords, bool> = HashMap::new();
-
-    let directions = parse_line("neeseswwnw");
-    floor.entry(Coords::zeroes()).or_insert(false);
-    for _ in 0..125 {
-        let mut to_add = vec![];
-        for (c, _) in &floor {
-            for d in &directions {
-                let a = Coords::from_direction(&d);
-                to_add.push(*c + a);
-            }
-        }
-        for c in to_add {
-            floor.entry(c).or_insert(false);
+// the floor after the initial flips followed by the floor after each day
+fn history(floor: Floor, days: usize) -> Vec<Floor> {
+    let mut floors = vec![floor];
+
+    for _ in 0..days {
+        let next = tick(floors.last().unwrap());
+        floors.push(next);
+    }
+
+    floors
+}
+
+fn render(args: &Args, floor: Floor) -> Result<(), ParseError> {
+    let days = args.parsed_option::<usize>("days")?.unwrap_or(0);
+    let size = args.parsed_option::<f64>("size")?.unwrap_or(10.0);
+    let floors = history(floor, days);
+    let renderer = Renderer::new(&floors, size);
+
+    if let Some(dir) = args.option("output") {
+        std::fs::create_dir_all(dir)?;
+        for (day, floor) in floors.iter().enumerate() {
+            let path = std::path::Path::new(dir).join(format!("day-{:03}.svg", day));
+            std::fs::write(path, renderer.frame(floor))?;
         }
+    } else if args.flag("animate") {
+        let seconds = args.parsed_option::<f64>("seconds")?.unwrap_or(0.2);
+        print!("{}", renderer.animation(&floors, seconds));
+    } else {
+        print!("{}", renderer.frame(floors.last().unwrap()));
     }
 
-    floor
+    Ok(())
 }
 
-fn count_black_neighbors(tile: &Coords, floor: &HashMap<Coords, bool>, ndirections: &Vec<Coords>) -> u64 {
-    let mut count = 0;
-    for d in ndirections {
-        if let Some(v) = floor.get(&(*tile + *d)) {
-            if *v {
-                count += 1;
+pub fn command(args: &Args) -> Result<(), ParseError> {
+    let input = parse_input(&args.input(include_str!("./data/input.txt"))?)?;
+
+    match args.positional(1) {
+        Some("render") => return render(args, flip_tiles(&input)),
+        Some("shortest") => {
+            for tile in &input {
+                println!("{}", path::to_string(&path::shortest_path(&get_tile(tile))));
             }
-        }
+            return Ok(());
+        },
+        _ => {},
     }
-    count
+
+    let days = args.parsed_option::<usize>("days")?.unwrap_or(100);
+
+    let floor = flip_tiles(&input);
+    println!("# of black tiles after flipping: {}", floor.len());
+
+    let floor = simulate(floor, days);
+    println!("# of black tiles after {} days: {}", days, floor.len());
+
+    Ok(())
 }
 
-fn tick(floor: HashMap<Coords, bool>) -> HashMap<Coords, bool> {
-    let mut new = floor.clone();
-    let ndirections = parse_line("neeseswwnw").into_iter()
-        .map(|d| Coords::from_direction(&d))
-        .collect::<Vec<_>>();
+#[cfg(test)]
+mod test {
+    use super::*;
 
-    for (tile, black) in &floor {
-        let count = count_black_neighbors(&tile, &floor, &ndirections);
+    #[test]
+    pub fn example_1_1() {
+        let input = parse_input(include_str!("./data/example.txt")).unwrap();
+        assert_eq!(10, flip_tiles(&input).len());
+    }
 
-        if *black && (count == 0 || count > 2) {
-            new.entry(*tile).and_modify(|v| *v = false);
-        }
+    #[test]
+    pub fn example_2_1() {
+        let input = parse_input(include_str!("./data/example.txt")).unwrap();
+        let floor = flip_tiles(&input);
 
-        if !*black && count == 2 {
-            new.entry(*tile).and_modify(|v| *v = true);
-        }
+        assert_eq!(15, simulate(floor.clone(), 1).len());
+        assert_eq!(37, simulate(floor.clone(), 10).len());
+        assert_eq!(2208, simulate(floor, 100).len());
     }
 
-    new
-}
+    #[test]
+    pub fn floors_can_be_rendered() {
+        let input = parse_input(include_str!("./data/example.txt")).unwrap();
+        let floors = history(flip_tiles(&input), 2);
+        let renderer = Renderer::new(&floors, 10.0);
 
-pub fn problem2() -> Result<(), ParseError> {
-    let input = parse_input();
+        let frame = renderer.frame(&floors[0]);
+        assert!(frame.starts_with("<svg "));
+        assert_eq!(10, frame.matches("class=\"black\"").count());
 
-    let mut floor = initialize_floor();
-    for tile in input.iter() {
-        let coords = get_tile(tile);
-        floor.entry(coords)
-            .and_modify(|v| *v = !*v)
-            .or_insert(true);
+        let animation = renderer.animation(&floors, 0.5);
+        assert_eq!(10 + 15 + 12, animation.matches("class=\"black\"").count());
+        assert_eq!(3, animation.matches("<set ").count());
+        assert_eq!(1, animation.matches("fill=\"freeze\"").count());
     }
 
-    let pb = ProgressBar::new(100);
-    for _ in 0..100 {
-        pb.inc(1);
-        floor = tick(floor);
+    #[test]
+    pub fn parser_reports_positions() {
+        use super::super::utils::hex::TokenError;
+
+        let input = "# a comment\n  ne e  sw # walk around\n\nnwx";
+        assert_eq!(TokenError { line: 4, column: 3, found: 'x' }, parse_direction_lines(input).unwrap_err());
+
+        let input = "# a comment\n  ne e  sw # walk around\n\nnw";
+        assert_eq!(vec![
+            vec![Direction::NorthEast, Direction::East, Direction::SouthWest],
+            vec![Direction::NorthWest],
+        ], parse_direction_lines(input).unwrap());
+
+        assert_eq!(TokenError { line: 1, column: 2, found: 'n' }, parse_direction_lines("snw").unwrap_err());
+        assert_eq!(TokenError { line: 2, column: 3, found: 'n' }, parse_direction_lines("e\nwen").unwrap_err());
+        assert_eq!(TokenError { line: 1, column: 2, found: ' ' }, parse_direction_lines("n e").unwrap_err());
     }
-    pb.finish_and_clear();
 
-    let result = floor.iter()
-        .filter(|(_, v)| **v)
-        .count();
+    #[test]
+    pub fn shortest_paths_lead_to_the_same_tile() {
+        let input = parse_input(include_str!("./data/example.txt")).unwrap();
 
-    println!("24/2: # of flipped tiles: {}", result);
-    Ok(())
+        for tile in &input {
+            let coords = get_tile(tile);
+            let path = path::shortest_path(&coords);
+
+            assert_eq!(coords, get_tile(&path));
+            assert_eq!(coords.distance(&Coords::zeroes()) as usize, path.len());
+            assert!(path.len() <= tile.len());
+        }
+
+        let path = path::shortest_path(&get_tile(&parse_direction_lines("nwwswee").unwrap()[0]));
+        assert_eq!("", path::to_string(&path));
+        let path = path::shortest_path(&get_tile(&parse_direction_lines("esenee").unwrap()[0]));
+        assert_eq!("eee", path::to_string(&path));
+    }
 }
@@ -0,0 +1,31 @@
+use super::super::utils::hex::Direction;
+use super::Coords;
+
+/// The shortest list of directions leading from the reference tile to the
+/// given tile.
+pub fn shortest_path(tile: &Coords) -> Vec<Direction> {
+    let mut position = Coords::zeroes();
+    let mut result = vec![];
+
+    // on a hex grid there is always a neighbor that is one step closer to
+    // the target, so walking greedily yields a shortest path.
+    while position != *tile {
+        let distance = position.distance(tile);
+        let d = Direction::ALL.iter()
+            .find(|d| (position + d.cube()).distance(tile) < distance)
+            .unwrap();
+
+        position = position + d.cube();
+        result.push(*d);
+    }
+
+    // group equal directions
+    result.sort_by_key(|d| Direction::ALL.iter().position(|a| a == d));
+    result
+}
+
+pub fn to_string(directions: &[Direction]) -> String {
+    directions.iter()
+        .map(|d| d.as_str())
+        .collect()
+}
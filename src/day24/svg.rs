@@ -0,0 +1,136 @@
+use std::fmt::Write;
+use super::super::utils::hex::{Cube, Offset, OffsetLayout};
+use super::Floor;
+
+const LAYOUT: OffsetLayout = OffsetLayout::OddRows;
+
+/// Draws floors as pointy-topped hexagons. All floors rendered with the same
+/// renderer share one view box, white tiles are drawn once as background.
+pub struct Renderer {
+    size: f64,
+    min: Offset,
+    max: Offset,
+}
+
+impl Renderer {
+    /// Creates a renderer large enough to show all given floors, `size` is
+    /// the distance from the center of a tile to its corners in pixels.
+    pub fn new(floors: &[Floor], size: f64) -> Renderer {
+        let offsets = floors.iter()
+            .flat_map(|f| f.iter())
+            .map(|c| c.to_offset(LAYOUT))
+            .collect::<Vec<_>>();
+
+        let cols = offsets.iter().map(|o| o.col);
+        let rows = offsets.iter().map(|o| o.row);
+
+        // leave a border of one white tile around the black ones
+        let min = Offset {
+            col: cols.clone().min().unwrap_or(0) - 1,
+            row: rows.clone().min().unwrap_or(0) - 1,
+        };
+        let max = Offset {
+            col: cols.max().unwrap_or(0) + 1,
+            row: rows.max().unwrap_or(0) + 1,
+        };
+
+        Renderer { size, min, max }
+    }
+
+    fn center(&self, c: &Cube) -> (f64, f64) {
+        let sqrt3 = 3f64.sqrt();
+        let x = self.size * (sqrt3 * c.x as f64 + sqrt3 / 2.0 * c.y as f64);
+        let y = self.size * (1.5 * c.y as f64);
+
+        (x, y)
+    }
+
+    fn hexagon(&self, c: &Cube, class: &str) -> String {
+        let (cx, cy) = self.center(c);
+
+        let points = (0..6)
+            .map(|i| {
+                let angle = (60.0 * i as f64 - 30.0).to_radians();
+                format!("{:.2},{:.2}", cx + self.size * angle.cos(), cy + self.size * angle.sin())
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        format!("<polygon class=\"{}\" points=\"{}\"/>", class, points)
+    }
+
+    fn header(&self) -> String {
+        let corners = [
+            Cube::from_offset(&self.min, LAYOUT),
+            Cube::from_offset(&Offset { col: self.max.col, row: self.min.row }, LAYOUT),
+            Cube::from_offset(&Offset { col: self.min.col, row: self.max.row }, LAYOUT),
+            Cube::from_offset(&self.max, LAYOUT),
+        ];
+
+        let xs = corners.iter().map(|c| self.center(c).0);
+        let ys = corners.iter().map(|c| self.center(c).1);
+        let min_x = xs.clone().fold(f64::MAX, f64::min) - 2.0 * self.size;
+        let max_x = xs.fold(f64::MIN, f64::max) + 2.0 * self.size;
+        let min_y = ys.clone().fold(f64::MAX, f64::min) - self.size;
+        let max_y = ys.fold(f64::MIN, f64::max) + self.size;
+
+        let mut out = String::new();
+        writeln!(out, "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{:.2} {:.2} {:.2} {:.2}\">",
+            min_x, min_y, max_x - min_x, max_y - min_y).unwrap();
+        writeln!(out, "<style>.white {{ fill: white; stroke: #999; stroke-width: 1 }} .black {{ fill: black; stroke: #999; stroke-width: 1 }}</style>").unwrap();
+        out
+    }
+
+    fn background(&self) -> String {
+        let mut out = String::from("<g>\n");
+
+        for row in self.min.row..=self.max.row {
+            for col in self.min.col..=self.max.col {
+                let c = Cube::from_offset(&Offset { col, row }, LAYOUT);
+                writeln!(out, "{}", self.hexagon(&c, "white")).unwrap();
+            }
+        }
+
+        out.push_str("</g>\n");
+        out
+    }
+
+    fn tiles(&self, floor: &Floor) -> String {
+        // sort the tiles so the output does not depend on the hash order
+        let mut tiles = floor.iter().collect::<Vec<_>>();
+        tiles.sort_by_key(|c| (c.y, c.x));
+
+        tiles.iter()
+            .map(|c| self.hexagon(c, "black") + "\n")
+            .collect()
+    }
+
+    /// A single picture of the given floor.
+    pub fn frame(&self, floor: &Floor) -> String {
+        format!("{}{}<g>\n{}</g>\n</svg>\n", self.header(), self.background(), self.tiles(floor))
+    }
+
+    /// All floors in one picture, each shown for the given number of seconds
+    /// in turn. The last floor stays visible at the end.
+    pub fn animation(&self, floors: &[Floor], seconds: f64) -> String {
+        let mut out = self.header();
+        out.push_str(&self.background());
+
+        for (i, floor) in floors.iter().enumerate() {
+            let begin = i as f64 * seconds;
+            let timing = if i + 1 == floors.len() {
+                "fill=\"freeze\"".to_string()
+            } else {
+                format!("dur=\"{}s\"", seconds)
+            };
+
+            writeln!(out, "<g visibility=\"hidden\">").unwrap();
+            writeln!(out, "<set attributeName=\"visibility\" to=\"visible\" begin=\"{}s\" {}/>", begin, timing).unwrap();
+            out.push_str(&self.tiles(floor));
+            writeln!(out, "</g>").unwrap();
+        }
+
+        out.push_str("</svg>\n");
+        out
+    }
+}
@@ -0,0 +1,80 @@
+use std::fmt;
+use super::{Field, Passport};
+use super::schema::Schema;
+
+#[derive(Debug, PartialEq)]
+pub enum WarningKind<'a> {
+    DuplicateKey(&'a str),
+    UnknownKey(&'a str),
+    MissingColon(&'a str),
+}
+
+/// Something suspicious in the input that did not prevent parsing. Tokens
+/// without a colon are dropped, repeated and unknown keys are kept so
+/// validation sees every value.
+#[derive(Debug, PartialEq)]
+pub struct Warning<'a> {
+    pub record: usize,
+    pub line: usize,
+    pub kind: WarningKind<'a>,
+}
+
+impl<'a> fmt::Display for Warning<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {} (record {}): ", self.line, self.record)?;
+
+        match self.kind {
+            WarningKind::DuplicateKey(k) => write!(f, "duplicate key '{}'", k),
+            WarningKind::UnknownKey(k) => write!(f, "unknown key '{}'", k),
+            WarningKind::MissingColon(t) => write!(f, "token without colon '{}'", t),
+        }
+    }
+}
+
+pub struct Batch<'a> {
+    pub passports: Vec<Passport<'a>>,
+    pub warnings: Vec<Warning<'a>>,
+}
+
+/// Parses a batch of records separated by blank lines. Line numbers start at 1.
+pub fn parse_batch<'a>(input: &'a str, schema: &Schema) -> Batch<'a> {
+    let mut passports = vec![];
+    let mut warnings = vec![];
+    let mut current: Option<Passport> = None;
+
+    for (i, line) in input.lines().enumerate() {
+        let line_number = i + 1;
+
+        if line.trim().is_empty() {
+            passports.extend(current.take());
+            continue;
+        }
+
+        let passport = current.get_or_insert_with(|| Passport { line: line_number, fields: vec![] });
+        let record = passports.len() + 1;
+
+        for token in line.split_whitespace() {
+            let warning = |kind| Warning { record, line: line_number, kind };
+
+            let (identifier, value) = match token.split_once(':') {
+                Some(kv) => kv,
+                None => {
+                    warnings.push(warning(WarningKind::MissingColon(token)));
+                    continue;
+                }
+            };
+
+            if passport.fields.iter().any(|f| f.identifier == identifier) {
+                warnings.push(warning(WarningKind::DuplicateKey(identifier)));
+            } else if schema.field(identifier).is_none() {
+                warnings.push(warning(WarningKind::UnknownKey(identifier)));
+            }
+
+            passport.fields.push(Field::new(identifier, value));
+        }
+    }
+
+    passports.extend(current.take());
+
+    Batch { passports, warnings }
+}
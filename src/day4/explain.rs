@@ -0,0 +1,50 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use super::{Passport, Failure};
+use super::schema::Schema;
+
+/// Failure counts by field across a batch of passports.
+pub struct Summary<'a> {
+    total: usize,
+    valid: usize,
+    failures: BTreeMap<&'a str, BTreeMap<Failure, usize>>,
+}
+
+impl<'a> Summary<'a> {
+    pub fn new(passports: &'a [Passport], schema: &'a Schema) -> Summary<'a> {
+        let mut valid = 0;
+        let mut failures: BTreeMap<&str, BTreeMap<Failure, usize>> = BTreeMap::new();
+
+        for p in passports {
+            let result = p.validate(schema);
+            if result.is_empty() {
+                valid += 1;
+            }
+
+            for f in result {
+                *failures.entry(f.identifier)
+                    .or_default()
+                    .entry(f.failure)
+                    .or_insert(0) += 1;
+            }
+        }
+
+        Summary { total: passports.len(), valid, failures }
+    }
+}
+
+impl<'a> fmt::Display for Summary<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} passports, {} valid, {} rejected", self.total, self.valid, self.total - self.valid)?;
+
+        for (identifier, counts) in &self.failures {
+            let counts = counts.iter()
+                .map(|(failure, n)| format!("{} {}", n, failure))
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(f, "{}: {}", identifier, counts)?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,19 +1,16 @@
+use std::fmt;
+use std::str::FromStr;
 use super::utils::ParseError;
+use super::cli::Args;
 use regex::Regex;
 
-fn get_validator(key: &str) -> Box<dyn FieldValidator> {
-    match key {
-        "byr" => Box::new(YearValidator { min: 1920, max: 2002 }),
-        "iyr" => Box::new(YearValidator { min: 2010, max: 2020 }),
-        "eyr" => Box::new(YearValidator { min: 2020, max: 2030 }),
-        "hgt" => Box::new(HeightValidator {}),
-        "hcl" => Box::new(PatternValidator { regex: Regex::new(r"^\#([0-9a-f]{6})$").unwrap() }),
-        "ecl" => Box::new(PatternValidator { regex: Regex::new(r"^(amb|blu|brn|gry|grn|hzl|oth)$").unwrap() }),
-        "pid" => Box::new(PatternValidator { regex: Regex::new(r"^\d{9}$").unwrap() }),
-        "cid" => Box::new(EmptyValidator { value: true }),
-        _ => Box::new(EmptyValidator { value: false }),
-    }
-}
+mod schema;
+mod explain;
+mod batch;
+
+use schema::Schema;
+use explain::Summary;
+use batch::parse_batch;
 
 struct Field<'a> {
     identifier: &'a str,
@@ -28,16 +25,66 @@ impl<'a> Field<'a> {
         Field { identifier, value }
     }
 
-    pub fn is_valid(&'a self) -> bool {
-        // TODO: optimize this, we should not instantiate the validators every
-        // time
-        let validator = get_validator(self.identifier);
-        validator.is_valid(self)
+    pub fn validate(&'a self, schema: &Schema) -> Result<(), Failure> {
+        schema.field(self.identifier)
+            .ok_or(Failure::UnknownField)
+            .and_then(|f| f.validator.validate(self))
+    }
+
+    pub fn is_valid(&'a self, schema: &Schema) -> bool {
+        self.validate(schema).is_ok()
     }
 }
 
+/// The reason a field was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Failure {
+    Missing,
+    UnknownField,
+    Unparsable,
+    OutOfRange,
+    WrongUnit,
+    BadPattern,
+    NotAllowed,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match self {
+            Failure::Missing => "missing",
+            Failure::UnknownField => "unknown field",
+            Failure::Unparsable => "unparsable",
+            Failure::OutOfRange => "out of range",
+            Failure::WrongUnit => "wrong unit",
+            Failure::BadPattern => "bad pattern",
+            Failure::NotAllowed => "not allowed",
+        };
+
+        write!(f, "{}", description)
+    }
+}
+
+/// A failure together with the field it occurred in.
+#[derive(Debug, PartialEq)]
+struct FieldFailure<'a> {
+    identifier: &'a str,
+    failure: Failure,
+}
+
 trait FieldValidator {
-    fn is_valid(&self, field: &Field) -> bool;
+    fn validate(&self, field: &Field) -> Result<(), Failure>;
+
+    fn is_valid(&self, field: &Field) -> bool {
+        self.validate(field).is_ok()
+    }
+}
+
+fn in_range(value: u32, min: u32, max: u32) -> Result<(), Failure> {
+    if value >= min && value <= max {
+        Ok(())
+    } else {
+        Err(Failure::OutOfRange)
+    }
 }
 
 struct EmptyValidator {
@@ -45,52 +92,67 @@ struct EmptyValidator {
 }
 
 impl FieldValidator for EmptyValidator {
-    fn is_valid(&self, _: &Field) -> bool {
-        self.value
+    fn validate(&self, _: &Field) -> Result<(), Failure> {
+        if self.value {
+            Ok(())
+        } else {
+            Err(Failure::NotAllowed)
+        }
     }
 }
 
 struct YearValidator {
-    min: i16,
-    max: i16,
+    min: u32,
+    max: u32,
 }
 
 impl FieldValidator for YearValidator {
-    fn is_valid(&self, field: &Field) -> bool {
-        field.value.parse::<i16>()
-            .map(|v| v >= self.min && v <= self.max)
-            .unwrap_or(false)
+    fn validate(&self, field: &Field) -> Result<(), Failure> {
+        let year = field.value.parse::<u32>().map_err(|_| Failure::Unparsable)?;
+        in_range(year, self.min, self.max)
     }
 }
 
-struct HeightValidator {
+struct Unit {
+    name: String,
+    min: u32,
+    max: u32,
 }
 
-impl FieldValidator for HeightValidator {
-    fn is_valid(&self, field: &Field) -> bool {
-        lazy_static!{
-            static ref RE: Regex = Regex::new(r"(\d*)(cm|in)").unwrap();
-        }
+// a number followed by a unit, e.g. a height like 150cm. like the original
+// height check the value only has to contain such a measure somewhere, so
+// "x190cm" is accepted as well.
+struct MeasureValidator {
+    units: Vec<Unit>,
+    regex: Regex,
+}
 
-        if !RE.is_match(&field.value) {
-            return false;
-        }
+impl MeasureValidator {
+    fn new(units: Vec<Unit>) -> Result<MeasureValidator, ParseError> {
+        let names = units.iter()
+            .map(|u| regex::escape(&u.name))
+            .collect::<Vec<_>>()
+            .join("|");
+        let regex = Regex::new(&format!(r"(\d*)({})", names))?;
+
+        Ok(MeasureValidator { units, regex })
+    }
+}
+
+impl FieldValidator for MeasureValidator {
+    fn validate(&self, field: &Field) -> Result<(), Failure> {
+        let cap = match self.regex.captures(field.value) {
+            Some(cap) => cap,
+            None if field.value.starts_with(|c: char| c.is_ascii_digit()) => return Err(Failure::WrongUnit),
+            None => return Err(Failure::Unparsable),
+        };
 
-        RE.captures(&field.value)
-            .map(|cap| {
-                let unit = &cap[2];
-                // TODO: Get rid of this unwrap
-                let size = cap[1].parse::<u32>().unwrap();
+        let size = cap[1].parse::<u32>().map_err(|_| Failure::Unparsable)?;
+        let unit = self.units.iter()
+            .find(|u| u.name == cap[2])
+            .ok_or(Failure::WrongUnit)?;
 
-                if unit == "cm" && size >= 150 && size <= 193 {
-                    true
-                } else if unit == "in" && size >= 59 && size <= 76 {
-                    true
-                } else {
-                    false
-                }
-            })
-            .unwrap_or(false)
+        in_range(size, unit.min, unit.max)
     }
 }
 
@@ -99,66 +161,78 @@ struct PatternValidator {
 }
 
 impl FieldValidator for PatternValidator {
-    fn is_valid(&self, field: &Field) -> bool {
-        self.regex.is_match(field.value)
+    fn validate(&self, field: &Field) -> Result<(), Failure> {
+        if self.regex.is_match(field.value) {
+            Ok(())
+        } else {
+            Err(Failure::BadPattern)
+        }
     }
 }
 
-struct Passport<'a> {
-    fields: Vec<Field<'a>>,
+struct EnumValidator {
+    values: Vec<String>,
 }
 
-fn parse_field<'a>(field: &'a str) -> Result<Field<'a>, ParseError> {
-    let key_value = field.split(':').collect::<Vec<_>>();
-
-    if key_value.len() != 2 {
-        Err(ParseError::new("foo"))
-    } else {
-        Ok(Field::new(key_value[0], key_value[1]))
+impl FieldValidator for EnumValidator {
+    fn validate(&self, field: &Field) -> Result<(), Failure> {
+        if self.values.iter().any(|v| v == field.value) {
+            Ok(())
+        } else {
+            Err(Failure::NotAllowed)
+        }
     }
 }
 
-impl<'a> Passport<'a> {
-    fn from_str(line: &'a str) -> Result<Passport<'a>, ParseError> {
-        let fields = line.split(|c| c == ' ' || c == '\n')
-            .filter(|v| !v.is_empty())
-            .map(parse_field)
-            .collect::<Result<Vec<_>, ParseError>>()?;
-
-        Ok(Passport { fields })
-    }
+struct Passport<'a> {
+    line: usize,
+    fields: Vec<Field<'a>>,
 }
 
 impl<'a> Passport<'a> {
-    fn has_required_fields(&self) -> bool {
-        let required_fields = vec!["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid"];
-
-        required_fields.iter()
-            .all(|&k| self.fields.iter().any(|f| f.identifier == k))
+    fn has_required_fields(&self, schema: &Schema) -> bool {
+        schema.required_fields()
+            .all(|k| self.fields.iter().any(|f| f.identifier == k))
     }
 
-    fn is_valid(&self) -> bool {
+    fn is_valid(&self, schema: &Schema) -> bool {
         self.fields
             .iter()
-            .all(|v| v.is_valid())
+            .all(|v| v.is_valid(schema))
+    }
+
+    /// Lists every reason this passport is rejected under the given schema,
+    /// an empty list means the passport is valid.
+    fn validate<'s>(&'s self, schema: &'s Schema) -> Vec<FieldFailure<'s>> {
+        let missing = schema.required_fields()
+            .filter(|k| !self.fields.iter().any(|f| f.identifier == *k))
+            .map(|identifier| FieldFailure { identifier, failure: Failure::Missing });
+
+        let invalid = self.fields.iter()
+            .filter_map(|f| f.validate(schema).err().map(|failure| FieldFailure { identifier: f.identifier, failure }));
+
+        missing.chain(invalid).collect()
     }
 }
 
-fn parse_input<'a>() -> Result<Vec<Passport<'a>>, ParseError> {
-    let input = include_str!("./data/input.txt");
+fn count_complete(passports: &[Passport], schema: &Schema) -> usize {
+    passports.iter()
+        .filter(|p| p.has_required_fields(schema))
+        .count()
+}
 
-    input.split("\n\n")
-        .filter(|v| !v.is_empty())
-        .map(|v| Passport::from_str(v))
-        .collect::<Result<Vec<_>, ParseError>>()
+fn count_valid(passports: &[Passport], schema: &Schema) -> usize {
+    passports.iter()
+        .filter(|p| p.has_required_fields(schema))
+        .filter(|p| p.is_valid(schema))
+        .count()
 }
 
 pub fn problem1() -> Result<usize, ParseError> {
-    let input = parse_input()?;
+    let schema = Schema::passport()?;
+    let input = parse_batch(include_str!("./data/input.txt"), &schema).passports;
 
-    let solution = input.iter()
-        .filter(|p| p.has_required_fields())
-        .count();
+    let solution = count_complete(&input, &schema);
 
     println!("4/1: # of 'valid' passports: {}", solution);
 
@@ -166,18 +240,40 @@ pub fn problem1() -> Result<usize, ParseError> {
 }
 
 pub fn problem2() -> Result<usize, ParseError> {
-    let input = parse_input()?;
+    let schema = Schema::passport()?;
+    let input = parse_batch(include_str!("./data/input.txt"), &schema).passports;
 
-    let solution = input.iter()
-        .filter(|p| p.has_required_fields())
-        .filter(|p| p.is_valid())
-        .count();
+    let solution = count_valid(&input, &schema);
 
     println!("4/2: # of 'valid' passports: {}", solution);
 
     Ok(solution)
 }
 
+pub fn command(args: &Args) -> Result<(), ParseError> {
+    let input = args.input(include_str!("./data/input.txt"))?;
+    let schema = match args.option("schema") {
+        Some(path) => Schema::from_str(&std::fs::read_to_string(path)?)?,
+        None => Schema::passport()?,
+    };
+    let batch = parse_batch(&input, &schema);
+    let passports = batch.passports;
+
+    for w in &batch.warnings {
+        println!("warning: {}", w);
+    }
+
+    if args.flag("explain") {
+        print!("{}", Summary::new(&passports, &schema));
+        return Ok(());
+    }
+
+    println!("# of passports with all required fields: {}", count_complete(&passports, &schema));
+    println!("# of valid passports: {}", count_valid(&passports, &schema));
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -186,13 +282,125 @@ mod test {
     pub fn valid_expiration_year_can_be_validated() {
         let field = Field::new("eyr", "2020");
 
-        assert_eq!(true, field.is_valid());
+        assert!(field.is_valid(&Schema::passport().unwrap()));
     }
 
     #[test]
     pub fn invalid_expiration_year_can_be_validated() {
         let field = Field::new("eyr", "2015");
 
-        assert_eq!(false, field.is_valid());
+        assert!(!field.is_valid(&Schema::passport().unwrap()));
+    }
+
+    #[test]
+    pub fn example_2_1() {
+        let schema = Schema::passport().unwrap();
+
+        let valid = parse_batch(include_str!("./data/valid.txt"), &schema).passports;
+        assert_eq!(4, count_valid(&valid, &schema));
+
+        let invalid = parse_batch(include_str!("./data/invalid.txt"), &schema).passports;
+        assert_eq!(0, count_valid(&invalid, &schema));
+    }
+
+    #[test]
+    pub fn schema_can_describe_other_credentials() {
+        let schema = Schema::from_str("
+            # a membership card
+            name    required  regex    ^[A-Z][a-z]+$
+            since   required  year     1990-2020
+            tier    optional  enum     gold silver
+            weight  optional  measure  kg:40-150 lb:90-330
+        ").unwrap();
+
+        let cards = parse_batch("name:Alice since:1999 tier:gold weight:200lb\n\nname:bob since:1999\n\nsince:2000 name:Carol weight:30kg", &schema).passports;
+
+        assert!(cards[0].has_required_fields(&schema) && cards[0].is_valid(&schema));
+        assert!(cards[1].has_required_fields(&schema) && !cards[1].is_valid(&schema));
+        assert!(cards[2].has_required_fields(&schema) && !cards[2].is_valid(&schema));
+    }
+
+    #[test]
+    pub fn failures_are_reported_per_field() {
+        let schema = Schema::passport().unwrap();
+        let passports = parse_batch("byr:19x iyr:2009 eyr:2025 hgt:190 hcl:#12345z ecl:xxx foo:bar\n\nhgt:80in", &schema).passports;
+
+        let failures = passports[0].validate(&schema)
+            .into_iter()
+            .map(|f| (f.identifier, f.failure))
+            .collect::<Vec<_>>();
+
+        assert_eq!(vec![
+            ("pid", Failure::Missing),
+            ("byr", Failure::Unparsable),
+            ("iyr", Failure::OutOfRange),
+            ("hgt", Failure::WrongUnit),
+            ("hcl", Failure::BadPattern),
+            ("ecl", Failure::NotAllowed),
+            ("foo", Failure::UnknownField),
+        ], failures);
+
+        let failures = passports[1].validate(&schema);
+        assert_eq!(7, failures.len());
+        assert_eq!(FieldFailure { identifier: "hgt", failure: Failure::OutOfRange }, failures[6]);
+
+        let summary = Summary::new(&passports, &schema).to_string();
+        assert!(summary.contains("2 passports, 0 valid, 2 rejected"));
+        assert!(summary.contains("hgt: 1 out of range, 1 wrong unit"));
+        assert!(summary.contains("pid: 2 missing"));
+    }
+
+    #[test]
+    pub fn batch_parser_reports_positions_and_warnings() {
+        use batch::{Warning, WarningKind};
+
+        let schema = Schema::passport().unwrap();
+        let input = "byr:1980 iyr:2012\nbyr:1990 pid\n\n\n  \nhgt:74in\nfoo:bar ecl:grn\n";
+        let batch = parse_batch(input, &schema);
+
+        assert_eq!(vec![1, 6], batch.passports.iter().map(|p| p.line).collect::<Vec<_>>());
+        assert_eq!(3, batch.passports[0].fields.len());
+        assert_eq!("1980", batch.passports[0].fields[0].value);
+        assert_eq!(3, batch.passports[1].fields.len());
+
+        assert_eq!(vec![
+            Warning { record: 1, line: 2, kind: WarningKind::DuplicateKey("byr") },
+            Warning { record: 1, line: 2, kind: WarningKind::MissingColon("pid") },
+            Warning { record: 2, line: 7, kind: WarningKind::UnknownKey("foo") },
+        ], batch.warnings);
+        assert_eq!("line 2 (record 1): duplicate key 'byr'", batch.warnings[0].to_string());
+    }
+
+    #[test]
+    pub fn duplicate_keys_are_validated() {
+        let schema = Schema::passport().unwrap();
+        let input = "byr:1980 iyr:2012 eyr:2025 hgt:180cm hcl:#123abc ecl:brn pid:000000001 byr:1800";
+        let batch = parse_batch(input, &schema);
+
+        assert_eq!(1, batch.warnings.len());
+        assert_eq!(0, count_valid(&batch.passports, &schema));
+
+        let failures = batch.passports[0].validate(&schema);
+        assert_eq!(1, failures.len());
+        assert_eq!("byr", failures[0].identifier);
+    }
+
+    #[test]
+    pub fn invalid_schemas_are_rejected() {
+        assert!(Schema::from_str("byr mandatory year 1920-2002").is_err());
+        assert!(Schema::from_str("byr required year").is_err());
+        assert!(Schema::from_str("hgt required measure cm").is_err());
+
+        let error = Schema::from_str("byr required year 1920-2002\n\n# again\nbyr optional any").err().unwrap();
+        assert_eq!("Field 'byr' in line 4 is already defined", error.to_string());
+        assert!(Schema::from_str("byr required date").is_err());
+        assert!(Schema::from_str("hcl required regex ^(#$").is_err());
+    }
+
+    #[test]
+    pub fn height_check_is_unanchored() {
+        let schema = Schema::passport().unwrap();
+        assert!(Field::new("hgt", "x190cm").is_valid(&schema));
+        assert!(!Field::new("hgt", "cm").is_valid(&schema));
     }
 }
@@ -0,0 +1,145 @@
+use std::str::FromStr;
+use regex::Regex;
+use super::super::utils::ParseError;
+use super::{FieldValidator, EmptyValidator, YearValidator, MeasureValidator, Unit, PatternValidator, EnumValidator};
+
+pub struct FieldSchema {
+    pub name: String,
+    pub required: bool,
+    pub validator: Box<dyn FieldValidator>,
+}
+
+/// Describes which fields a credential consists of and how to validate them.
+///
+/// A schema is read line by line, each line has the form
+///
+///   <field> <required|optional> <type> [arguments]
+///
+/// with one of the following types:
+///
+///   year 1920-2002            a year within the given range
+///   measure cm:150-193 in:59-76
+///                             a number followed by one of the given units,
+///                             each unit with its own range
+///   regex ^\d{9}$             a value matching the regular expression
+///   enum amb blu brn          one of the listed values
+///   any                       any value
+///
+/// Empty lines and lines starting with '#' are ignored, every field may only
+/// be defined once.
+pub struct Schema {
+    fields: Vec<FieldSchema>,
+}
+
+impl Schema {
+    /// The schema of the passports in the puzzle.
+    pub fn passport() -> Result<Schema, ParseError> {
+        Schema::from_str(include_str!("./data/schema.txt"))
+    }
+
+    pub fn field(&self, name: &str) -> Option<&FieldSchema> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+
+    pub fn required_fields(&self) -> impl Iterator<Item = &str> {
+        self.fields.iter()
+            .filter(|f| f.required)
+            .map(|f| f.name.as_str())
+    }
+}
+
+fn parse_range(s: &str) -> Result<(u32, u32), ParseError> {
+    let error = || ParseError::new(&format!("Not a valid range: '{}'", s));
+    let (min, max) = s.split_once('-').ok_or_else(error)?;
+    let min = min.parse::<u32>().map_err(|_| error())?;
+    let max = max.parse::<u32>().map_err(|_| error())?;
+
+    Ok((min, max))
+}
+
+fn parse_unit(s: &str) -> Result<Unit, ParseError> {
+    let (name, range) = s.split_once(':')
+        .ok_or_else(|| ParseError::new(&format!("Not a valid unit: '{}'", s)))?;
+    let (min, max) = parse_range(range)?;
+
+    Ok(Unit { name: name.to_string(), min, max })
+}
+
+fn parse_validator(kind: &str, arguments: &str) -> Result<Box<dyn FieldValidator>, ParseError> {
+    let validator: Box<dyn FieldValidator> = match kind {
+        "year" => {
+            let (min, max) = parse_range(arguments)?;
+            Box::new(YearValidator { min, max })
+        },
+        "measure" => {
+            let units = arguments.split_whitespace()
+                .map(parse_unit)
+                .collect::<Result<Vec<_>, ParseError>>()?;
+            Box::new(MeasureValidator::new(units)?)
+        },
+        "regex" => Box::new(PatternValidator { regex: Regex::new(arguments)? }),
+        "enum" => {
+            let values = arguments.split_whitespace().map(|v| v.to_string()).collect();
+            Box::new(EnumValidator { values })
+        },
+        "any" => Box::new(EmptyValidator { value: true }),
+        _ => return Err(ParseError::new(&format!("Unknown field type: '{}'", kind))),
+    };
+
+    Ok(validator)
+}
+
+// splits off the first whitespace separated word of s
+fn next_word(s: &str) -> (&str, &str) {
+    let s = s.trim_start();
+    match s.find(char::is_whitespace) {
+        Some(i) => (&s[..i], &s[i..]),
+        None => (s, ""),
+    }
+}
+
+fn parse_field_schema(line: &str) -> Result<FieldSchema, ParseError> {
+    let error = || ParseError::new(&format!("Not a valid field definition: '{}'", line));
+
+    let (name, rest) = next_word(line);
+    let (presence, rest) = next_word(rest);
+    let (kind, arguments) = next_word(rest);
+
+    let required = match presence {
+        "required" => true,
+        "optional" => false,
+        _ => return Err(error()),
+    };
+
+    if kind.is_empty() {
+        return Err(error());
+    }
+
+    let validator = parse_validator(kind, arguments.trim())?;
+
+    Ok(FieldSchema { name: name.to_string(), required, validator })
+}
+
+impl FromStr for Schema {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Schema, ParseError> {
+        let mut fields: Vec<FieldSchema> = vec![];
+
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let field = parse_field_schema(line)?;
+            if fields.iter().any(|f| f.name == field.name) {
+                return Err(ParseError::new(&format!("Field '{}' in line {} is already defined", field.name, i + 1)));
+            }
+
+            fields.push(field);
+        }
+
+        Ok(Schema { fields })
+    }
+}
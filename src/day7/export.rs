@@ -0,0 +1,126 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::str::FromStr;
+use super::super::utils::ParseError;
+use super::Rule;
+
+pub enum Format {
+    Dot,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Format, ParseError> {
+        match s {
+            "dot" => Ok(Format::Dot),
+            "json" => Ok(Format::Json),
+            _ => Err(ParseError::new(&format!("Unknown export format: {}", s))),
+        }
+    }
+}
+
+// all colours in order of their first appearance together with the edges
+// between them, limited to the selected colours if there is a selection.
+fn subgraph<'a>(rules: &[Rule<'a>], selection: Option<&HashSet<&'a str>>) -> (Vec<&'a str>, Vec<(&'a str, usize, &'a str)>) {
+    let selected = |b: &str| selection.map(|s| s.contains(b)).unwrap_or(true);
+
+    let mut nodes = vec![];
+    let mut seen = HashSet::new();
+    let mut edges = vec![];
+
+    for (bag, content) in rules {
+        if selected(bag) && seen.insert(*bag) {
+            nodes.push(*bag);
+        }
+
+        for (n, b) in content {
+            if selected(bag) && selected(b) {
+                if seen.insert(*b) {
+                    nodes.push(*b);
+                }
+                edges.push((*bag, *n, *b));
+            }
+        }
+    }
+
+    (nodes, edges)
+}
+
+// DOT strings only know \" as an escape sequence, graphviz itself reads \\
+// and \n in labels. other control characters can't be written at all.
+fn quote_dot(s: &str) -> String {
+    let mut result = String::from("\"");
+
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            c if c.is_control() => result.push(' '),
+            c => result.push(c),
+        }
+    }
+
+    result.push('"');
+    result
+}
+
+fn quote_json(s: &str) -> String {
+    let mut result = String::from("\"");
+
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            c if c.is_control() => write!(result, "\\u{:04x}", c as u32).unwrap(),
+            c => result.push(c),
+        }
+    }
+
+    result.push('"');
+    result
+}
+
+fn dot(nodes: &[&str], edges: &[(&str, usize, &str)]) -> String {
+    let mut out = String::new();
+    writeln!(out, "digraph bags {{").unwrap();
+
+    for n in nodes {
+        writeln!(out, "    {};", quote_dot(n)).unwrap();
+    }
+
+    for (from, count, to) in edges {
+        writeln!(out, "    {} -> {} [label=\"{}\"];", quote_dot(from), quote_dot(to), count).unwrap();
+    }
+
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+fn json(nodes: &[&str], edges: &[(&str, usize, &str)]) -> String {
+    let nodes = nodes.iter()
+        .map(|n| quote_json(n))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let edges = edges.iter()
+        .map(|(from, count, to)| format!("{{\"from\":{},\"to\":{},\"count\":{}}}", quote_json(from), quote_json(to), count))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    format!("{{\"nodes\":[{}],\"edges\":[{}]}}\n", nodes, edges)
+}
+
+/// Renders the containment graph, each edge points from a bag to one of its
+/// contents and is labelled with the number of bags.
+pub fn export<'a>(rules: &[Rule<'a>], selection: Option<&HashSet<&'a str>>, format: &Format) -> String {
+    let (nodes, edges) = subgraph(rules, selection);
+
+    match format {
+        Format::Dot => dot(&nodes, &edges),
+        Format::Json => json(&nodes, &edges),
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::{HashMap, HashSet};
+use super::super::utils::ParseError;
+use super::Rule;
+
+/// The bag rules as a directed graph keyed by colour. Each colour maps to the
+/// bags it contains and, in reverse, to the bags that directly contain it.
+pub struct BagGraph<'a> {
+    contents: HashMap<&'a str, Vec<(usize, &'a str)>>,
+    containers: HashMap<&'a str, Vec<(usize, &'a str)>>,
+}
+
+impl<'a> BagGraph<'a> {
+    pub fn new(rules: &[Rule<'a>]) -> Result<BagGraph<'a>, ParseError> {
+        let mut contents = HashMap::new();
+        let mut containers: HashMap<&str, Vec<(usize, &str)>> = HashMap::new();
+
+        for (bag, content) in rules {
+            if contents.insert(*bag, content.clone()).is_some() {
+                return Err(ParseError::new(&format!("More than one rule for bag {}", bag)));
+            }
+
+            for (n, b) in content {
+                containers.entry(*b).or_default().push((*n, *bag));
+            }
+        }
+
+        Ok(BagGraph { contents, containers })
+    }
+
+    pub fn contents(&self, bag: &str) -> Option<&Vec<(usize, &'a str)>> {
+        self.contents.get(bag)
+    }
+
+    pub fn containers(&self, bag: &str) -> &[(usize, &'a str)] {
+        self.containers.get(bag).map(|v| v.as_slice()).unwrap_or(&[])
+    }
+
+    /// All colours that eventually contain at least one bag of the given colour.
+    pub fn ancestors(&self, bag: &str) -> HashSet<&'a str> {
+        let mut result = HashSet::new();
+        let mut queue = vec![bag];
+
+        while let Some(b) = queue.pop() {
+            for (_, container) in self.containers(b) {
+                if result.insert(*container) {
+                    queue.push(container);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// All colours that are eventually contained in a bag of the given colour.
+    pub fn descendants(&self, bag: &str) -> HashSet<&'a str> {
+        let mut result = HashSet::new();
+        let mut queue = vec![bag];
+
+        while let Some(b) = queue.pop() {
+            for (_, content) in self.contents(b).map(|v| v.as_slice()).unwrap_or(&[]) {
+                if result.insert(*content) {
+                    queue.push(content);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// The number of bags inside one bag of the given colour.
+    pub fn count_contents(&self, bag: &'a str) -> Result<usize, ParseError> {
+        let mut memo = HashMap::new();
+        self.count_bags(bag, &mut memo).map(|v| v - 1)
+    }
+
+    // counts the bag itself and everything inside it, remembering the result
+    // for each colour so shared subtrees are only counted once.
+    fn count_bags(&self, bag: &'a str, memo: &mut HashMap<&'a str, usize>) -> Result<usize, ParseError> {
+        if let Some(n) = memo.get(bag) {
+            return Ok(*n);
+        }
+
+        let contents = self.contents(bag)
+            .ok_or_else(|| ParseError::new(&format!("No rule for bag {}", bag)))?;
+
+        let mut number_of_bags: usize = 1;
+        for (n, b) in contents {
+            number_of_bags = self.count_bags(b, memo)?
+                .checked_mul(*n)
+                .and_then(|v| v.checked_add(number_of_bags))
+                .ok_or_else(|| ParseError::new("bag count overflows"))?;
+        }
+
+        memo.insert(bag, number_of_bags);
+        Ok(number_of_bags)
+    }
+}
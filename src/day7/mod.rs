@@ -1,6 +1,17 @@
 use std::collections::HashSet;
 use regex::Regex;
 use super::utils::ParseError;
+use super::cli::Args;
+
+mod graph;
+mod validate;
+mod export;
+
+use graph::BagGraph;
+use validate::validate;
+use export::{export, Format};
+
+type Rule<'a> = (&'a str, Vec<(usize, &'a str)>);
 
 fn parse_content(content: &str) -> Result<(usize, &str), ParseError> {
     lazy_static!{
@@ -13,7 +24,7 @@ fn parse_content(content: &str) -> Result<(usize, &str), ParseError> {
     Ok((amount, description))
 }
 
-fn parse_rule(rule: &str) -> Result<(&str, Vec<(usize, &str)>), ParseError> {
+fn parse_rule(rule: &str) -> Result<Rule<'_>, ParseError> {
     let mut rule_split: Vec<&str> = rule.split(" bags contain ").collect();
 
     let contents = rule_split.pop().ok_or(ParseError::new(&format!("Not a rule: '{}'", rule)))?;
@@ -33,8 +44,7 @@ fn parse_rule(rule: &str) -> Result<(&str, Vec<(usize, &str)>), ParseError> {
     Ok((bag, contents))
 }
 
-fn parse_input() -> Result<Vec<(&'static str, Vec<(usize, &'static str)>)>, ParseError> {
-    let input = include_str!("./data/input.txt");
+fn parse_input(input: &str) -> Result<Vec<Rule<'_>>, ParseError> {
     input
         .lines()
         .filter(|v| *v != "")
@@ -42,65 +52,197 @@ fn parse_input() -> Result<Vec<(&'static str, Vec<(usize, &'static str)>)>, Pars
         .collect::<Result<Vec<_>, ParseError>>()
 }
 
-fn contains_bag(rule: &Vec<(usize, &str)>, search: &Vec<&str>) -> bool {
-    rule.iter().any(|(_, bag)| search.contains(bag))
+// validates the rules before building the graph so counting never runs into
+// cycles, undefined bags or more bags than fit into a usize.
+fn checked_graph<'a>(rules: &[Rule<'a>], root: &'a str) -> Result<BagGraph<'a>, ParseError> {
+    let validation = validate(rules, root);
+
+    if validation.has_errors() {
+        return Err(ParseError::new(&format!("Inconsistent bag rules:\n{}", validation)));
+    }
+
+    let graph = BagGraph::new(rules)?;
+    graph.count_contents(root)?;
+
+    Ok(graph)
 }
 
 pub fn problem1() -> Result<(), ParseError> {
-    let input = parse_input()?;
+    let input = parse_input(include_str!("./data/input.txt"))?;
+    let graph = checked_graph(&input, "shiny gold")?;
+
+    let result = graph.ancestors("shiny gold").len();
+
+    println!("7/1: # of colors of bags that can contain at least one 'shiny gold': {}", result);
+
+    Ok(())
+}
+
+pub fn problem2() -> Result<(), ParseError> {
+    let input = parse_input(include_str!("./data/input.txt"))?;
+    let graph = checked_graph(&input, "shiny gold")?;
 
-    let mut search = vec!["shiny gold"];
-    let mut previous_result = 0;
-    let mut relevant_bags: HashSet<&str> = HashSet::new();
+    let result = graph.count_contents("shiny gold")?;
 
-    loop {
-        let foo = input.iter()
-            .filter(|(_, content)| contains_bag(&content, &search))
-            .map(|(bag, _)| bag)
-            .collect::<Vec<_>>();
+    println!("7/2: # of bags one 'shiny gold' bag contains: {}", result);
 
-        search = foo.iter().map(|v| **v).collect::<Vec<_>>();
-        relevant_bags.extend(foo.into_iter().collect::<HashSet<_>>());
+    Ok(())
+}
+
+// the colours reachable from the root, the colours reaching it or both,
+// always including the root itself.
+fn select<'a>(rules: &[Rule<'a>], root: &'a str, direction: &str) -> Result<HashSet<&'a str>, ParseError> {
+    let graph = BagGraph::new(rules)?;
+
+    let mut selection = match direction {
+        "from" => graph.descendants(root),
+        "to" => graph.ancestors(root),
+        "both" => graph.descendants(root).union(&graph.ancestors(root)).cloned().collect(),
+        _ => return Err(ParseError::new(&format!("Unknown direction: {}", direction))),
+    };
+
+    selection.insert(root);
+    Ok(selection)
+}
 
-        if relevant_bags.len() == previous_result {
-            break;
+pub fn command(args: &Args) -> Result<(), ParseError> {
+    let input = args.input(include_str!("./data/input.txt"))?;
+    let rules = parse_input(&input)?;
+    let bag = args.option("bag").unwrap_or("shiny gold");
+
+    if args.positional(1) == Some("check") {
+        let validation = validate(&rules, bag);
+        print!("{}", validation);
+        if !validation.has_errors() {
+            println!("no errors found");
         }
-        previous_result = relevant_bags.len();
+        return Ok(());
     }
 
-    let result = relevant_bags.len();
+    if args.positional(1) == Some("export") {
+        let format = args.parsed_option::<Format>("format")?.unwrap_or(Format::Dot);
+        let selection = match args.option("root") {
+            Some(root) => Some(select(&rules, root, args.option("direction").unwrap_or("from"))?),
+            None => None,
+        };
+        print!("{}", export(&rules, selection.as_ref(), &format));
+        return Ok(());
+    }
 
-    println!("7/1: # of colors of bags that can contain at least one 'shiny gold': {}", result);
+    let graph = checked_graph(&rules, bag)?;
+
+    println!("# of colors of bags that can contain at least one '{}': {}", bag, graph.ancestors(bag).len());
+    println!("# of bags one '{}' bag contains: {}", bag, graph.count_contents(bag)?);
 
     Ok(())
 }
 
-fn count_bags(bag: &str, rules: &Vec<(&str, Vec<(usize, &str)>)>) -> Result<usize, ParseError> {
-    let relevant_rules = rules.iter()
-        .filter(|(b, _)| *b == bag)
-        .collect::<Vec<_>>();
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn example_1_1() {
+        let rules = parse_input(include_str!("./data/example.txt")).unwrap();
+        let graph = BagGraph::new(&rules).unwrap();
 
-    if relevant_rules.len() != 1 {
-        return Err(ParseError::new(&format!("No or more than one rule for bag {}", bag)));
+        assert_eq!(4, graph.ancestors("shiny gold").len());
+        assert_eq!(0, graph.ancestors("light red").len());
+        assert_eq!(2, graph.containers("muted yellow").len());
     }
 
-    let relevant_rule = relevant_rules[0];
+    #[test]
+    pub fn example_2_1() {
+        let rules = parse_input(include_str!("./data/example.txt")).unwrap();
+        let graph = BagGraph::new(&rules).unwrap();
 
-    let number_of_bags: usize = relevant_rule.1.iter()
-        .map(|(n, b)| count_bags(b, rules).map(|v| v * n))
-        .collect::<Result<Vec<_>, ParseError>>()?
-        .into_iter()
-        .sum::<usize>() + 1;
+        assert_eq!(32, graph.count_contents("shiny gold").unwrap());
+        assert_eq!(7, graph.count_contents("dark olive").unwrap());
+    }
 
-    Ok(number_of_bags)
-}
+    #[test]
+    pub fn example_2_2() {
+        let rules = parse_input(include_str!("./data/example2.txt")).unwrap();
+        let graph = BagGraph::new(&rules).unwrap();
 
-pub fn problem2() -> Result<(), ParseError> {
-    let rules = parse_input()?;
+        assert_eq!(126, graph.count_contents("shiny gold").unwrap());
+        assert!(graph.count_contents("plaid").is_err());
+    }
 
-    let result = count_bags(&"shiny gold", &rules)? - 1;
+    #[test]
+    pub fn bag_counts_that_overflow_are_rejected() {
+        let colours = ["c0", "c1", "c2", "c3", "c4", "c5", "c6", "c7", "c8"];
+        let mut input = colours.windows(2)
+            .map(|w| format!("{} bags contain 1000 {} bags.\n", w[0], w[1]))
+            .collect::<String>();
+        input.push_str("c8 bags contain no other bags.\n");
+        let rules = parse_input(&input).unwrap();
+
+        let graph = BagGraph::new(&rules).unwrap();
+        assert_eq!(1000 + 1000 * 1000, graph.count_contents("c6").unwrap());
+        assert_eq!("bag count overflows", graph.count_contents("c0").err().unwrap().to_string());
+        assert!(checked_graph(&rules, "c0").is_err());
+    }
 
-    println!("7/2: # of bags one 'shiny gold' bag contains: {}", result);
+    #[test]
+    pub fn validation_reports_inconsistent_rules() {
+        let input = "light red bags contain 1 bright white bag, 2 muted yellow bags.
+bright white bags contain 1 shiny gold bag.
+shiny gold bags contain 2 muted yellow bags.
+muted yellow bags contain 1 bright white bag, 3 faded blue bags.
+muted yellow bags contain no other bags.
+dotted black bags contain no other bags.";
+        let rules = parse_input(input).unwrap();
+        let validation = validate(&rules, "shiny gold");
+
+        assert!(validation.has_errors());
+        assert_eq!(vec![vec!["bright white", "shiny gold", "muted yellow", "bright white"]], validation.cycles);
+        assert_eq!(vec!["faded blue"], validation.undefined);
+        assert_eq!(vec!["muted yellow"], validation.duplicates);
+        assert_eq!(vec!["dotted black"], validation.unreachable);
+        assert!(checked_graph(&rules, "shiny gold").is_err());
+    }
 
-    Ok(())
+    #[test]
+    pub fn validation_accepts_consistent_rules() {
+        let rules = parse_input(include_str!("./data/example.txt")).unwrap();
+        let validation = validate(&rules, "shiny gold");
+
+        assert!(!validation.has_errors());
+        assert!(validation.unreachable.is_empty());
+        assert!(!validate(&rules, "plaid").undefined.is_empty());
+    }
+
+    #[test]
+    pub fn graph_can_be_exported() {
+        let rules = parse_input(include_str!("./data/example.txt")).unwrap();
+
+        let selection = select(&rules, "shiny gold", "from").unwrap();
+        let dot = export(&rules, Some(&selection), &Format::Dot);
+        assert_eq!("digraph bags {
+    \"shiny gold\";
+    \"dark olive\";
+    \"vibrant plum\";
+    \"faded blue\";
+    \"dotted black\";
+    \"shiny gold\" -> \"dark olive\" [label=\"1\"];
+    \"shiny gold\" -> \"vibrant plum\" [label=\"2\"];
+    \"dark olive\" -> \"faded blue\" [label=\"3\"];
+    \"dark olive\" -> \"dotted black\" [label=\"4\"];
+    \"vibrant plum\" -> \"faded blue\" [label=\"5\"];
+    \"vibrant plum\" -> \"dotted black\" [label=\"6\"];
+}
+", dot);
+
+        let selection = select(&rules, "bright white", "to").unwrap();
+        let json = export(&rules, Some(&selection), &Format::Json);
+        assert_eq!("{\"nodes\":[\"light red\",\"bright white\",\"dark orange\"],\"edges\":[{\"from\":\"light red\",\"to\":\"bright white\",\"count\":1},{\"from\":\"dark orange\",\"to\":\"bright white\",\"count\":3}]}\n", json);
+
+        let json = export(&rules, None, &Format::Json);
+        assert_eq!(13, json.matches("\"from\"").count());
+
+        let rules = vec![("odd\t\"bag\"", vec![])];
+        assert!(export(&rules, None, &Format::Dot).contains("\"odd \\\"bag\\\"\";"));
+        assert!(export(&rules, None, &Format::Json).contains("\"odd\\u0009\\\"bag\\\"\""));
+    }
 }
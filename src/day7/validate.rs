@@ -0,0 +1,151 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use super::Rule;
+
+/// Problems found in a set of bag rules. Cycles, undefined colours and
+/// duplicate definitions make counting impossible, unreachable colours are
+/// only reported for information.
+#[derive(Debug, Default)]
+pub struct Validation<'a> {
+    pub cycles: Vec<Vec<&'a str>>,
+    pub undefined: Vec<&'a str>,
+    pub duplicates: Vec<&'a str>,
+    pub unreachable: Vec<&'a str>,
+}
+
+impl<'a> Validation<'a> {
+    pub fn has_errors(&self) -> bool {
+        !self.cycles.is_empty() || !self.undefined.is_empty() || !self.duplicates.is_empty()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Visit {
+    InProgress,
+    Done,
+}
+
+struct CycleSearch<'a, 'r> {
+    contents: &'r HashMap<&'a str, &'r Vec<(usize, &'a str)>>,
+    visited: HashMap<&'a str, Visit>,
+    path: Vec<&'a str>,
+    cycles: Vec<Vec<&'a str>>,
+}
+
+impl<'a, 'r> CycleSearch<'a, 'r> {
+    fn visit(&mut self, bag: &'a str) {
+        match self.visited.get(bag) {
+            Some(Visit::Done) => return,
+            Some(Visit::InProgress) => {
+                // the bag is on the current path, everything from its first
+                // occurrence onwards forms the cycle.
+                let start = self.path.iter().position(|b| *b == bag).unwrap();
+                let mut cycle = self.path[start..].to_vec();
+                cycle.push(bag);
+                self.cycles.push(cycle);
+                return;
+            },
+            None => {},
+        }
+
+        self.visited.insert(bag, Visit::InProgress);
+        self.path.push(bag);
+
+        if let Some(contents) = self.contents.get(bag) {
+            for (_, b) in contents.iter() {
+                self.visit(b);
+            }
+        }
+
+        self.path.pop();
+        self.visited.insert(bag, Visit::Done);
+    }
+}
+
+fn reachable<'a>(start: &'a str, edges: &HashMap<&'a str, Vec<&'a str>>) -> HashSet<&'a str> {
+    let mut result = HashSet::new();
+    let mut queue = vec![start];
+
+    while let Some(b) = queue.pop() {
+        if result.insert(b) {
+            if let Some(next) = edges.get(b) {
+                queue.extend(next.iter());
+            }
+        }
+    }
+
+    result
+}
+
+/// Checks the rules for consistency. Colours that neither contain nor are
+/// contained in a bag of the root colour are reported as unreachable.
+pub fn validate<'a>(rules: &[Rule<'a>], root: &'a str) -> Validation<'a> {
+    let mut validation = Validation::default();
+    let mut contents = HashMap::new();
+
+    // only the first definition of a bag is followed when searching cycles
+    for (bag, content) in rules {
+        if contents.contains_key(bag) {
+            if !validation.duplicates.contains(bag) {
+                validation.duplicates.push(bag);
+            }
+        } else {
+            contents.insert(*bag, content);
+        }
+    }
+
+    let referenced = rules.iter()
+        .flat_map(|(_, content)| content.iter().map(|(_, b)| *b))
+        .chain(std::iter::once(root));
+    for b in referenced {
+        if !contents.contains_key(b) && !validation.undefined.contains(&b) {
+            validation.undefined.push(b);
+        }
+    }
+
+    let mut search = CycleSearch { contents: &contents, visited: HashMap::new(), path: vec![], cycles: vec![] };
+    for (bag, _) in rules {
+        search.visit(bag);
+    }
+    validation.cycles = search.cycles;
+
+    let mut forward: HashMap<&str, Vec<&str>> = HashMap::new();
+    let mut backward: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (bag, content) in rules {
+        for (_, b) in content {
+            forward.entry(*bag).or_default().push(b);
+            backward.entry(*b).or_default().push(bag);
+        }
+    }
+
+    let connected = reachable(root, &forward).union(&reachable(root, &backward)).cloned().collect::<HashSet<_>>();
+    let mut seen = HashSet::new();
+    validation.unreachable = rules.iter()
+        .map(|(bag, _)| *bag)
+        .filter(|b| !connected.contains(b) && seen.insert(*b))
+        .collect();
+
+    validation
+}
+
+impl<'a> fmt::Display for Validation<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for cycle in &self.cycles {
+            writeln!(f, "error: cycle {}", cycle.join(" -> "))?;
+        }
+
+        for b in &self.undefined {
+            writeln!(f, "error: bag '{}' is referenced but never defined", b)?;
+        }
+
+        for b in &self.duplicates {
+            writeln!(f, "error: bag '{}' is defined more than once", b)?;
+        }
+
+        for b in &self.unreachable {
+            writeln!(f, "info: bag '{}' is unreachable", b)?;
+        }
+
+        Ok(())
+    }
+}
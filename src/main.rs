@@ -32,6 +32,7 @@ mod day23;
 mod day24;
 mod day25;
 mod utils;
+mod cli;
 
 fn run() -> Result<(), utils::ParseError> {
   day25::problem1()?;
@@ -41,7 +42,14 @@ fn run() -> Result<(), utils::ParseError> {
 }
 
 fn main() {
-  match run() {
+  let args = std::env::args().skip(1).collect::<Vec<_>>();
+  let result = if args.is_empty() {
+    run()
+  } else {
+    cli::run(&args)
+  };
+
+  match result {
     Err(err) => println!("Error occurred: {}", err),
     _ => {}
   }
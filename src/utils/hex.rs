@@ -0,0 +1,562 @@
+//! Coordinates on a hexagonal grid.
+//!
+//! Positions are stored in cube coordinates (x + y + z = 0). For pointy-topped
+//! grids x grows towards the east and y towards the south east, for
+//! flat-topped grids x grows towards the south east and y towards the south.
+//! See https://www.redblobgames.com/grids/hexagons/ for the background.
+
+use std::fmt;
+use std::ops::{Add, Sub, Mul};
+use std::str::FromStr;
+use super::ParseError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cube {
+  pub x: i64,
+  pub y: i64,
+  pub z: i64,
+}
+
+/// Axial coordinates drop the redundant third cube coordinate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Axial {
+  pub q: i64,
+  pub r: i64,
+}
+
+/// Column and row in a rectangular layout of the grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Offset {
+  pub col: i64,
+  pub row: i64,
+}
+
+/// Which rows (pointy-topped) or columns (flat-topped) are shoved by half a
+/// tile in an offset layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OffsetLayout {
+  OddRows,
+  EvenRows,
+  OddColumns,
+  EvenColumns,
+}
+
+impl Cube {
+  pub fn new(x: i64, y: i64, z: i64) -> Self {
+    Self { x, y, z }
+  }
+
+  pub fn zeroes() -> Self {
+    Self { x: 0, y: 0, z: 0 }
+  }
+
+  /// The sum of the absolute cube coordinates. This is twice the number of
+  /// steps to the origin, use `distance` to count steps.
+  pub fn coordinate_sum(&self) -> u64 {
+    (self.x.abs() + self.y.abs() + self.z.abs()) as u64
+  }
+
+  /// The number of steps between two tiles. Every step changes two of the
+  /// three coordinates by one, so this is half of the coordinate sum of the difference.
+  pub fn distance(&self, other: &Cube) -> u64 {
+    (*self - *other).coordinate_sum() / 2
+  }
+
+  pub fn neighbors(&self) -> Vec<Cube> {
+    Direction::ALL.iter()
+      .map(|d| *self + d.cube())
+      .collect()
+  }
+
+  /// Rotates by 60 degrees clockwise around the origin.
+  pub fn rotate_right(&self) -> Cube {
+    Cube::new(-self.y, -self.z, -self.x)
+  }
+
+  /// Rotates by 60 degrees counterclockwise around the origin.
+  pub fn rotate_left(&self) -> Cube {
+    Cube::new(-self.z, -self.x, -self.y)
+  }
+
+  /// Rotates by `steps` times 60 degrees around `center`, clockwise for
+  /// positive steps.
+  pub fn rotate_around(&self, center: &Cube, steps: i32) -> Cube {
+    let mut v = *self - *center;
+
+    for _ in 0..steps.rem_euclid(6) {
+      v = v.rotate_right();
+    }
+
+    *center + v
+  }
+
+  /// All tiles at exactly the given distance, starting in the south west and
+  /// going around counterclockwise.
+  pub fn ring(&self, radius: u64) -> Vec<Cube> {
+    if radius == 0 {
+      return vec![*self];
+    }
+
+    let mut result = vec![];
+    let mut tile = *self + Direction::SouthWest.cube() * radius as i64;
+
+    for d in &Direction::ALL {
+      for _ in 0..radius {
+        result.push(tile);
+        tile = tile + d.cube();
+      }
+    }
+
+    result
+  }
+
+  /// All tiles within the given distance, ring by ring from the center.
+  pub fn spiral(&self, radius: u64) -> Vec<Cube> {
+    (0..=radius)
+      .flat_map(|r| self.ring(r))
+      .collect()
+  }
+
+  /// The tiles on a straight line from this tile to the other, both
+  /// included.
+  pub fn line_to(&self, other: &Cube) -> Vec<Cube> {
+    let n = self.distance(other);
+    if n == 0 {
+      return vec![*self];
+    }
+
+    // nudge the points a little so points exactly between two tiles are
+    // always rounded to the same side.
+    let lerp = |a: i64, b: i64, t: f64, nudge: f64| {
+      a as f64 + (b - a) as f64 * t + nudge
+    };
+
+    (0..=n)
+      .map(|i| {
+        let t = i as f64 / n as f64;
+        Cube::round(lerp(self.x, other.x, t, 1e-6), lerp(self.y, other.y, t, 1e-6), lerp(self.z, other.z, t, -2e-6))
+      })
+      .collect()
+  }
+
+  /// The tile containing the given fractional cube coordinates.
+  pub fn round(x: f64, y: f64, z: f64) -> Cube {
+    let (mut rx, mut ry, mut rz) = (x.round(), y.round(), z.round());
+    let (dx, dy, dz) = ((rx - x).abs(), (ry - y).abs(), (rz - z).abs());
+
+    if dx > dy && dx > dz {
+      rx = -ry - rz;
+    } else if dy > dz {
+      ry = -rx - rz;
+    } else {
+      rz = -rx - ry;
+    }
+
+    Cube::new(rx as i64, ry as i64, rz as i64)
+  }
+
+  pub fn to_axial(self) -> Axial {
+    Axial { q: self.x, r: self.y }
+  }
+
+  pub fn from_axial(a: &Axial) -> Cube {
+    Cube::new(a.q, a.r, -a.q - a.r)
+  }
+
+  pub fn to_offset(self, layout: OffsetLayout) -> Offset {
+    let (q, r) = (self.x, self.y);
+
+    match layout {
+      OffsetLayout::OddRows => Offset { col: q + (r - (r & 1)) / 2, row: r },
+      OffsetLayout::EvenRows => Offset { col: q + (r + (r & 1)) / 2, row: r },
+      OffsetLayout::OddColumns => Offset { col: q, row: r + (q - (q & 1)) / 2 },
+      OffsetLayout::EvenColumns => Offset { col: q, row: r + (q + (q & 1)) / 2 },
+    }
+  }
+
+  pub fn from_offset(o: &Offset, layout: OffsetLayout) -> Cube {
+    let (col, row) = (o.col, o.row);
+
+    let (q, r) = match layout {
+      OffsetLayout::OddRows => (col - (row - (row & 1)) / 2, row),
+      OffsetLayout::EvenRows => (col - (row + (row & 1)) / 2, row),
+      OffsetLayout::OddColumns => (col, row - (col - (col & 1)) / 2),
+      OffsetLayout::EvenColumns => (col, row - (col + (col & 1)) / 2),
+    };
+
+    Cube::from_axial(&Axial { q, r })
+  }
+}
+
+impl Add for Cube {
+  type Output = Self;
+
+  fn add(self, other: Self) -> Self {
+    Self {
+      x: self.x + other.x,
+      y: self.y + other.y,
+      z: self.z + other.z,
+    }
+  }
+}
+
+impl Sub for Cube {
+  type Output = Self;
+
+  fn sub(self, other: Self) -> Self {
+    Self {
+      x: self.x - other.x,
+      y: self.y - other.y,
+      z: self.z - other.z,
+    }
+  }
+}
+
+impl Mul<i64> for Cube {
+  type Output = Self;
+
+  fn mul(self, k: i64) -> Self {
+    Self {
+      x: self.x * k,
+      y: self.y * k,
+      z: self.z * k,
+    }
+  }
+}
+
+/// Directions on a pointy-topped grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+  East,
+  NorthEast,
+  NorthWest,
+  West,
+  SouthWest,
+  SouthEast,
+}
+
+impl Direction {
+  /// All directions, counterclockwise starting in the east.
+  pub const ALL: [Direction; 6] = [
+    Direction::East,
+    Direction::NorthEast,
+    Direction::NorthWest,
+    Direction::West,
+    Direction::SouthWest,
+    Direction::SouthEast,
+  ];
+
+  pub fn cube(&self) -> Cube {
+    let (x, y, z) = match self {
+      Direction::East => (1, 0, -1),
+      Direction::NorthEast => (1, -1, 0),
+      Direction::NorthWest => (0, -1, 1),
+      Direction::West => (-1, 0, 1),
+      Direction::SouthWest => (-1, 1, 0),
+      Direction::SouthEast => (0, 1, -1),
+    };
+
+    Cube::new(x, y, z)
+  }
+
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Direction::East => "e",
+      Direction::NorthEast => "ne",
+      Direction::NorthWest => "nw",
+      Direction::West => "w",
+      Direction::SouthWest => "sw",
+      Direction::SouthEast => "se",
+    }
+  }
+}
+
+impl FromStr for Direction {
+  type Err = ParseError;
+
+  fn from_str(s: &str) -> Result<Self, ParseError> {
+    Direction::ALL.iter()
+      .find(|d| d.as_str() == s)
+      .cloned()
+      .ok_or_else(|| ParseError::new(&format!("Unknown direction: '{}'", s)))
+  }
+}
+
+/// Directions on a flat-topped grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FlatDirection {
+  North,
+  NorthEast,
+  SouthEast,
+  South,
+  SouthWest,
+  NorthWest,
+}
+
+impl FlatDirection {
+  /// All directions, clockwise starting in the north.
+  pub const ALL: [FlatDirection; 6] = [
+    FlatDirection::North,
+    FlatDirection::NorthEast,
+    FlatDirection::SouthEast,
+    FlatDirection::South,
+    FlatDirection::SouthWest,
+    FlatDirection::NorthWest,
+  ];
+
+  pub fn cube(&self) -> Cube {
+    let (x, y, z) = match self {
+      FlatDirection::North => (0, -1, 1),
+      FlatDirection::NorthEast => (1, -1, 0),
+      FlatDirection::SouthEast => (1, 0, -1),
+      FlatDirection::South => (0, 1, -1),
+      FlatDirection::SouthWest => (-1, 1, 0),
+      FlatDirection::NorthWest => (-1, 0, 1),
+    };
+
+    Cube::new(x, y, z)
+  }
+
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      FlatDirection::North => "n",
+      FlatDirection::NorthEast => "ne",
+      FlatDirection::SouthEast => "se",
+      FlatDirection::South => "s",
+      FlatDirection::SouthWest => "sw",
+      FlatDirection::NorthWest => "nw",
+    }
+  }
+}
+
+impl FromStr for FlatDirection {
+  type Err = ParseError;
+
+  fn from_str(s: &str) -> Result<Self, ParseError> {
+    FlatDirection::ALL.iter()
+      .find(|d| d.as_str() == s)
+      .cloned()
+      .ok_or_else(|| ParseError::new(&format!("Unknown direction: '{}'", s)))
+  }
+}
+
+/// An unexpected character in a list of directions. Lines and columns start
+/// at 1.
+#[derive(Debug, PartialEq)]
+pub struct TokenError {
+  pub line: usize,
+  pub column: usize,
+  pub found: char,
+}
+
+impl fmt::Display for TokenError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "Unexpected character '{}' in line {}, column {}", self.found, self.line, self.column)
+  }
+}
+
+impl From<TokenError> for ParseError {
+  fn from(error: TokenError) -> Self {
+    ParseError::new(&error.to_string())
+  }
+}
+
+// directions that are written as abbreviations of one or two letters
+trait Abbreviated: Copy + 'static {
+  fn all() -> &'static [Self];
+  fn abbreviation(&self) -> &'static str;
+}
+
+impl Abbreviated for Direction {
+  fn all() -> &'static [Self] {
+    &Direction::ALL
+  }
+
+  fn abbreviation(&self) -> &'static str {
+    self.as_str()
+  }
+}
+
+impl Abbreviated for FlatDirection {
+  fn all() -> &'static [Self] {
+    &FlatDirection::ALL
+  }
+
+  fn abbreviation(&self) -> &'static str {
+    self.as_str()
+  }
+}
+
+// splits one line of directions without separators like "nwwswee" into the
+// single directions, always taking the longest abbreviation. whitespace
+// between directions is ignored and everything after a '#' is a comment.
+fn tokenize<D: Abbreviated>(line: &str, line_number: usize) -> Result<Vec<D>, TokenError> {
+  let chars = line.chars().collect::<Vec<_>>();
+  let mut result = vec![];
+  let mut i = 0;
+
+  while i < chars.len() {
+    let c = chars[i];
+
+    if c == '#' {
+      break;
+    }
+
+    if c.is_whitespace() {
+      i += 1;
+      continue;
+    }
+
+    let d = D::all().iter()
+      .filter(|d| {
+        let a = d.abbreviation().chars().collect::<Vec<_>>();
+        chars[i..].starts_with(&a)
+      })
+      .max_by_key(|d| d.abbreviation().len());
+
+    match d {
+      Some(d) => {
+        result.push(*d);
+        i += d.abbreviation().chars().count();
+      },
+      None => {
+        // if c starts an abbreviation the character after it is the one
+        // that doesn't fit
+        let prefix = D::all().iter().any(|d| d.abbreviation().starts_with(c));
+        let column = if prefix && i + 1 < chars.len() { i + 1 } else { i };
+        return Err(TokenError { line: line_number, column: column + 1, found: chars[column] });
+      },
+    }
+  }
+
+  Ok(result)
+}
+
+fn tokenize_lines<D: Abbreviated>(input: &str) -> Result<Vec<Vec<D>>, TokenError> {
+  let mut result = vec![];
+
+  for (i, line) in input.lines().enumerate() {
+    let directions = tokenize(line, i + 1)?;
+    if !directions.is_empty() {
+      result.push(directions);
+    }
+  }
+
+  Ok(result)
+}
+
+/// Parses directions like `nwwswee` on a pointy-topped grid.
+pub fn parse_directions(s: &str) -> Result<Vec<Direction>, TokenError> {
+  tokenize(s, 1)
+}
+
+/// Parses directions like `nnesw` on a flat-topped grid.
+pub fn parse_flat_directions(s: &str) -> Result<Vec<FlatDirection>, TokenError> {
+  tokenize(s, 1)
+}
+
+/// Parses one list of directions on a pointy-topped grid per line, lines
+/// without any directions are skipped.
+pub fn parse_direction_lines(input: &str) -> Result<Vec<Vec<Direction>>, TokenError> {
+  tokenize_lines(input)
+}
+
+/// Parses one list of directions on a flat-topped grid per line, lines
+/// without any directions are skipped.
+pub fn parse_flat_direction_lines(input: &str) -> Result<Vec<Vec<FlatDirection>>, TokenError> {
+  tokenize_lines(input)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  pub fn distances() {
+    let a = Cube::new(1, -3, 2);
+    let b = Cube::new(-2, 0, 2);
+
+    assert_eq!(6, a.coordinate_sum());
+    assert_eq!(3, a.distance(&b));
+    assert_eq!(0, b.distance(&b));
+  }
+
+  #[test]
+  pub fn distance_to_origin_is_half_the_coordinate_sum() {
+    for c in Cube::new(1, -3, 2).spiral(3) {
+      assert_eq!(c.coordinate_sum() / 2, c.distance(&Cube::zeroes()));
+    }
+  }
+
+  #[test]
+  pub fn coordinate_conversions() {
+    let layouts = [OffsetLayout::OddRows, OffsetLayout::EvenRows, OffsetLayout::OddColumns, OffsetLayout::EvenColumns];
+
+    for c in Cube::zeroes().spiral(4) {
+      assert_eq!(c, Cube::from_axial(&c.to_axial()));
+      for l in &layouts {
+        assert_eq!(c, Cube::from_offset(&c.to_offset(*l), *l));
+      }
+    }
+
+    // the south west neighbor of the origin is in the row below, on an odd
+    // row layout that row is shoved to the right.
+    let sw = Direction::SouthWest.cube();
+    assert_eq!(Offset { col: -1, row: 1 }, sw.to_offset(OffsetLayout::OddRows));
+    assert_eq!(Offset { col: 0, row: 1 }, sw.to_offset(OffsetLayout::EvenRows));
+  }
+
+  #[test]
+  pub fn rings_and_spirals() {
+    let center = Cube::new(2, -1, -1);
+
+    assert_eq!(vec![center], center.ring(0));
+    for r in 1..5 {
+      let ring = center.ring(r);
+      assert_eq!(6 * r as usize, ring.len());
+      assert!(ring.iter().all(|c| c.distance(&center) == r));
+    }
+
+    assert_eq!(1 + 6 + 12 + 18, center.spiral(3).len());
+    assert_eq!(center.ring(1).len(), center.neighbors().len());
+  }
+
+  #[test]
+  pub fn rotation() {
+    let c = Direction::East.cube();
+
+    assert_eq!(Direction::SouthEast.cube(), c.rotate_right());
+    assert_eq!(Direction::NorthEast.cube(), c.rotate_left());
+    assert_eq!(c, c.rotate_around(&Cube::zeroes(), 6));
+    assert_eq!(c.rotate_left(), c.rotate_around(&Cube::zeroes(), -1));
+
+    let center = Cube::new(1, 1, -2);
+    assert_eq!(center + Direction::West.cube(), (center + c).rotate_around(&center, 3));
+  }
+
+  #[test]
+  pub fn lines() {
+    let a = Cube::zeroes();
+    let b = Direction::East.cube() * 3 + Direction::SouthEast.cube();
+    let line = a.line_to(&b);
+
+    assert_eq!(5, line.len());
+    assert_eq!(a, line[0]);
+    assert_eq!(b, line[4]);
+    for w in line.windows(2) {
+      assert_eq!(1, w[0].distance(&w[1]));
+    }
+  }
+
+  #[test]
+  pub fn parsing() {
+    use Direction::*;
+
+    assert_eq!(vec![NorthWest, West, SouthWest, East, East], parse_directions("nwwswee").unwrap());
+    assert_eq!(vec![FlatDirection::North, FlatDirection::NorthEast, FlatDirection::South, FlatDirection::SouthWest],
+      parse_flat_directions("nnessw").unwrap());
+    assert_eq!(TokenError { line: 1, column: 3, found: 'n' }, parse_directions("nwn").unwrap_err());
+    assert_eq!(TokenError { line: 1, column: 4, found: 'x' }, parse_flat_directions("ne x").unwrap_err());
+    assert_eq!(2, parse_flat_direction_lines("n\n\n# only a comment\nsw s").unwrap().len());
+  }
+}
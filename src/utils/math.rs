@@ -0,0 +1,104 @@
+use std::fmt;
+use num::{BigInt, Integer, Signed, Zero};
+use super::ParseError;
+
+/// The congruence x = remainder (mod modulus).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Congruence {
+  pub remainder: BigInt,
+  pub modulus: BigInt,
+}
+
+impl Congruence {
+  pub fn new<T: Into<BigInt>>(remainder: T, modulus: T) -> Congruence {
+    Congruence { remainder: remainder.into(), modulus: modulus.into() }
+  }
+}
+
+impl fmt::Display for Congruence {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "x = {} (mod {})", self.remainder, self.modulus)
+  }
+}
+
+/// Solves a system of congruences with the chinese remainder theorem.
+///
+/// The moduli do not have to be pairwise coprime. Two congruences are merged
+/// with the extended euclidean algorithm: for x = a (mod m) and x = b (mod n)
+/// with g = gcd(m, n) = m * p + n * q a solution exists iff g divides b - a,
+/// and it is unique modulo lcm(m, n).
+///
+/// The result is the smallest non-negative solution together with the
+/// modulus all solutions share.
+pub fn chinese_remainder(congruences: &[Congruence]) -> Result<Congruence, ParseError> {
+  let mut result = Congruence::new(0, 1);
+
+  for c in congruences {
+    if !c.modulus.is_positive() {
+      return Err(ParseError::new(&format!("Modulus must be positive: {}", c)));
+    }
+
+    let remainder = c.remainder.mod_floor(&c.modulus);
+    let egcd = result.modulus.extended_gcd(&c.modulus);
+    let difference = &remainder - &result.remainder;
+
+    if !difference.is_multiple_of(&egcd.gcd) {
+      return Err(ParseError::new(&format!(
+        "Inconsistent congruences: {} and {}", result, c)));
+    }
+
+    let step = &c.modulus / &egcd.gcd;
+    let lcm = &result.modulus * &step;
+    let k = (difference / &egcd.gcd * egcd.x).mod_floor(&step);
+
+    result.remainder = (&result.remainder + &result.modulus * k).mod_floor(&lcm);
+    result.modulus = lcm;
+  }
+
+  Ok(result)
+}
+
+/// Whether x satisfies all congruences.
+pub fn satisfies(x: &BigInt, congruences: &[Congruence]) -> bool {
+  congruences.iter()
+    .all(|c| (x - &c.remainder).mod_floor(&c.modulus).is_zero())
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  pub fn coprime_moduli() {
+    let system = vec![Congruence::new(0, 3), Congruence::new(3, 4), Congruence::new(4, 5)];
+    let solution = chinese_remainder(&system).unwrap();
+
+    assert_eq!(Congruence::new(39, 60), solution);
+    assert!(satisfies(&solution.remainder, &system));
+  }
+
+  #[test]
+  pub fn non_coprime_moduli() {
+    let system = vec![Congruence::new(3, 6), Congruence::new(-1, 4), Congruence::new(5, 10)];
+
+    assert_eq!(Congruence::new(15, 60), chinese_remainder(&system).unwrap());
+
+    let inconsistent = vec![Congruence::new(1, 6), Congruence::new(2, 4)];
+    assert!(chinese_remainder(&inconsistent).is_err());
+    assert!(chinese_remainder(&[Congruence::new(1, 0)]).is_err());
+  }
+
+  #[test]
+  pub fn big_moduli() {
+    let p: BigInt = "1000000000000000000000007".parse().unwrap();
+    let q: BigInt = "1000000000000000000000009".parse().unwrap();
+    let system = vec![
+      Congruence { remainder: BigInt::from(1), modulus: p.clone() },
+      Congruence { remainder: BigInt::from(2), modulus: q.clone() },
+    ];
+    let solution = chinese_remainder(&system).unwrap();
+
+    assert_eq!(&p * &q, solution.modulus);
+    assert!(satisfies(&solution.remainder, &system));
+  }
+}
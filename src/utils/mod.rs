@@ -3,6 +3,9 @@ use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
 
+pub mod hex;
+pub mod math;
+
 #[allow(dead_code)]
 pub fn read_file(filename: &str) -> String {
   let mut f = File::open(filename).expect("file not found");
@@ -80,6 +83,18 @@ impl From<ParseFloatError> for ParseError {
   }
 }
 
+impl From<std::io::Error> for ParseError {
+  fn from(error: std::io::Error) -> Self {
+    ParseError::new(&format!("Unable to read input: {}", error))
+  }
+}
+
+impl From<regex::Error> for ParseError {
+  fn from(error: regex::Error) -> Self {
+    ParseError::new(&format!("Invalid regular expression: {}", error))
+  }
+}
+
 impl From<ParseError> for Error {
   fn from(_error: ParseError) -> Self {
     Error::new("Unable to parse something")